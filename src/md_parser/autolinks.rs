@@ -0,0 +1,283 @@
+//! Extended autolinks follow the GitHub Flavored Markdown spec:
+//! <https://github.github.com/gfm/#autolinks-extension->. URLs that start with `www.`, `http://`
+//! or `https://`, and email addresses, are links even without `<` and `>` around them. They are
+//! found in the plaintext of the parsed document, since they can only start at the beginning of a
+//! line, after whitespace, or after `*`, `_`, `~` or `(`.
+
+use crate::*;
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Replaces the URLs and email addresses in the plaintext of `md` with [MarkdownInline::Autolink]s.
+/// The text of links is left alone, since links can't contain other links.
+pub fn find_extended_autolinks<'a>(md: Vec<Markdown<'a>>) -> Vec<Markdown<'a>> {
+    md.into_iter()
+        .map(|bit| match bit {
+            Markdown::Heading(size, line) => Markdown::Heading(size, find_in_text(line)),
+            Markdown::UnorderedList(items) => Markdown::UnorderedList(find_in_items(items)),
+            Markdown::OrderedList(items) => Markdown::OrderedList(find_in_items(items)),
+            Markdown::Paragraph(text) => Markdown::Paragraph(find_in_text(text)),
+            Markdown::BlockQuote(blocks) => Markdown::BlockQuote(find_extended_autolinks(blocks)),
+            Markdown::Table(Table {
+                header,
+                alignments,
+                rows,
+            }) => {
+                let find_in_row =
+                    |row: Vec<MarkdownText<'a>>| row.into_iter().map(find_in_text).collect();
+                Markdown::Table(Table {
+                    header: find_in_row(header),
+                    alignments,
+                    rows: rows.into_iter().map(find_in_row).collect(),
+                })
+            }
+            Markdown::FootnoteDefinition(FootnoteDefinition { label, blocks }) => {
+                Markdown::FootnoteDefinition(FootnoteDefinition {
+                    label,
+                    blocks: find_extended_autolinks(blocks),
+                })
+            }
+            Markdown::Footnotes(footnotes) => Markdown::Footnotes(
+                footnotes
+                    .into_iter()
+                    .map(|footnote| Footnote {
+                        blocks: find_extended_autolinks(footnote.blocks),
+                        ..footnote
+                    })
+                    .collect(),
+            ),
+            Markdown::Codeblock(..)
+            | Markdown::ThematicBreak
+            | Markdown::LinkDefinition(_)
+            | Markdown::FrontMatter(_) => bit,
+        })
+        .collect()
+}
+
+fn find_in_items(items: Vec<ListItem<'_>>) -> Vec<ListItem<'_>> {
+    items
+        .into_iter()
+        .map(|item| ListItem {
+            checked: item.checked,
+            blocks: find_extended_autolinks(item.blocks),
+        })
+        .collect()
+}
+
+fn find_in_text(text: MarkdownText<'_>) -> MarkdownText<'_> {
+    let mut result = vec![];
+    for part in text {
+        // Plaintext after a line break or emphasis starts after whitespace or a `*`, `_` or `~`.
+        let is_at_boundary = matches!(
+            result.last(),
+            None | Some(
+                MarkdownInline::SoftBreak
+                    | MarkdownInline::HardBreak
+                    | MarkdownInline::Bold(_)
+                    | MarkdownInline::Italic(_)
+                    | MarkdownInline::Strikethrough(_)
+            )
+        );
+        match part {
+            MarkdownInline::Bold(text) => result.push(MarkdownInline::Bold(find_in_text(text))),
+            MarkdownInline::Italic(text) => result.push(MarkdownInline::Italic(find_in_text(text))),
+            MarkdownInline::Strikethrough(text) => {
+                result.push(MarkdownInline::Strikethrough(find_in_text(text)))
+            }
+            MarkdownInline::Plaintext(text) => split_plaintext(text, is_at_boundary, &mut result),
+            part => result.push(part),
+        }
+    }
+    result
+}
+
+/// Appends `text` to `result`, split into plaintext and autolinks.
+fn split_plaintext<'a>(
+    text: Cow<'a, str>,
+    mut is_at_boundary: bool,
+    result: &mut MarkdownText<'a>,
+) {
+    let slice = |range: Range<usize>| match &text {
+        Cow::Borrowed(it) => Cow::Borrowed(&it[range]),
+        Cow::Owned(it) => Cow::Owned(it[range].to_owned()),
+    };
+    let mut start = 0;
+    for (index, it) in text.char_indices() {
+        if index >= start && is_at_boundary {
+            if let Some((len, url)) = match_extended_autolink(&text[index..]) {
+                if start < index {
+                    result.push(MarkdownInline::Plaintext(slice(start..index)));
+                }
+                result.push(MarkdownInline::Autolink(Autolink {
+                    text: slice(index..index + len),
+                    url: url.into(),
+                }));
+                start = index + len;
+            }
+        }
+        is_at_boundary = it.is_whitespace() || "*_~(".contains(it);
+    }
+    if start < text.len() {
+        result.push(MarkdownInline::Plaintext(slice(start..text.len())));
+    }
+}
+
+/// Returns the length of the URL or email address at the start of `text`, and the URL it links
+/// to, eg: `http://www.example.com` for `www.example.com`.
+pub fn match_extended_autolink(text: &str) -> Option<(usize, String)> {
+    let (prefix, domain_start) = if text.starts_with("www.") {
+        ("http://", 0)
+    } else if text.starts_with("http://") {
+        ("", "http://".len())
+    } else if text.starts_with("https://") {
+        ("", "https://".len())
+    } else {
+        let len = match_email(text)?;
+        return Some((len, format!("mailto:{}", &text[..len])));
+    };
+    let end = text
+        .find(|it: char| it.is_whitespace() || it == '<')
+        .unwrap_or(text.len());
+    let link = trim_trailing_punctuation(&text[..end]);
+    let domain = &link[domain_start..];
+    let domain = &domain[..domain
+        .find(|it: char| !is_domain_char(it))
+        .unwrap_or(domain.len())];
+    if !is_valid_domain(domain) {
+        return None;
+    }
+    Some((link.len(), format!("{}{}", prefix, link)))
+}
+
+/// Returns the length of the email address at the start of `text`. Only a `.` may follow the
+/// address, and it is not part of it.
+fn match_email(text: &str) -> Option<usize> {
+    let local = text
+        .find(|it: char| !(it.is_ascii_alphanumeric() || ".+-_".contains(it)))
+        .unwrap_or(text.len());
+    if local == 0 || !text[local..].starts_with('@') {
+        return None;
+    }
+    let domain = &text[local + 1..];
+    let domain = &domain[..domain
+        .find(|it: char| !is_domain_char(it))
+        .unwrap_or(domain.len())];
+    let domain = domain.strip_suffix('.').unwrap_or(domain);
+    if !is_valid_domain(domain) || domain.ends_with(['-', '_']) {
+        return None;
+    }
+    Some(local + 1 + domain.len())
+}
+
+fn is_domain_char(it: char) -> bool {
+    it.is_alphanumeric() || matches!(it, '-' | '_' | '.')
+}
+
+/// A domain has at least one `.`, and the two segments at the end can't contain `_`.
+fn is_valid_domain(domain: &str) -> bool {
+    let segments = domain.split('.').collect::<Vec<_>>();
+    segments.len() > 1
+        && segments.iter().all(|it| !it.is_empty())
+        && segments.iter().rev().take(2).all(|it| !it.contains('_'))
+}
+
+/// Removes the punctuation that is more likely to end the sentence than the URL, eg: the `.` in
+/// `Visit www.example.com.`. A `)` is only removed when it has no matching `(` in the URL, and an
+/// entity like `&amp;` at the end is removed as a whole.
+fn trim_trailing_punctuation(link: &str) -> &str {
+    let mut link = link;
+    loop {
+        let trimmed = match link.chars().next_back() {
+            Some('?' | '!' | '.' | ',' | ':' | '*' | '_' | '~') => &link[..link.len() - 1],
+            Some(')') if link.matches(')').count() > link.matches('(').count() => {
+                &link[..link.len() - 1]
+            }
+            Some(';') => {
+                let name =
+                    link[..link.len() - 1].trim_end_matches(|it: char| it.is_ascii_alphanumeric());
+                match name.strip_suffix('&') {
+                    Some(before) if name.len() + 1 < link.len() => before,
+                    _ => return link,
+                }
+            }
+            _ => return link,
+        };
+        link = trimmed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_extended_autolink() {
+        assert_eq!(
+            match_extended_autolink("www.commonmark.org/help for"),
+            Some((23, String::from("http://www.commonmark.org/help")))
+        );
+        assert_eq!(
+            match_extended_autolink("https://example.com/a_(b)."),
+            Some((25, String::from("https://example.com/a_(b)")))
+        );
+        assert_eq!(
+            match_extended_autolink("www.google.com/search?q=Markup+(business)))"),
+            Some((
+                41,
+                String::from("http://www.google.com/search?q=Markup+(business)")
+            ))
+        );
+        assert_eq!(
+            match_extended_autolink("www.google.com/search?q=commonmark&hl;"),
+            Some((
+                34,
+                String::from("http://www.google.com/search?q=commonmark")
+            ))
+        );
+        assert_eq!(
+            match_extended_autolink("www.a.b<c"),
+            Some((7, String::from("http://www.a.b")))
+        );
+        assert_eq!(
+            match_extended_autolink("a.b-c_d@a.b."),
+            Some((11, String::from("mailto:a.b-c_d@a.b")))
+        );
+        assert_eq!(match_extended_autolink("www.a_b.c_d"), None);
+        assert_eq!(match_extended_autolink("http://localhost"), None);
+        assert_eq!(match_extended_autolink("a.b-c_d@a.b-"), None);
+        assert_eq!(match_extended_autolink("a.b-c_d@a.b_"), None);
+        assert_eq!(match_extended_autolink("example.com"), None);
+    }
+
+    #[test]
+    fn test_find_extended_autolinks() {
+        assert_eq!(
+            try_parse_markdown("See www.example.com, me@example.com and *https://a.com*.\n"),
+            Ok(vec![Markdown::Paragraph(vec![
+                MarkdownInline::Plaintext("See ".into()),
+                MarkdownInline::Autolink(Autolink {
+                    text: "www.example.com".into(),
+                    url: "http://www.example.com".into()
+                }),
+                MarkdownInline::Plaintext(", ".into()),
+                MarkdownInline::Autolink(Autolink {
+                    text: "me@example.com".into(),
+                    url: "mailto:me@example.com".into()
+                }),
+                MarkdownInline::Plaintext(" and ".into()),
+                MarkdownInline::Italic(vec![MarkdownInline::Autolink(Autolink {
+                    text: "https://a.com".into(),
+                    url: "https://a.com".into()
+                })]),
+                MarkdownInline::Plaintext(".".into()),
+            ])])
+        );
+        assert_eq!(
+            render_markdown("xwww.a.com `www.b.com` [www.c.com](/c) (www.d.com)\n"),
+            String::from(concat!(
+                "<p>xwww.a.com <code>www.b.com</code> <a href=\"/c\">www.c.com</a> ",
+                "(<a href=\"http://www.d.com\">www.d.com</a>)</p>"
+            ))
+        );
+    }
+}
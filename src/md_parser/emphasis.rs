@@ -0,0 +1,400 @@
+//! Emphasis is resolved with the delimiter run algorithm from the CommonMark spec:
+//! <https://spec.commonmark.org/0.30/#emphasis-and-strong-emphasis>. The inline parsers split
+//! text into [InlineToken]s, leaving runs of `*`, `_` and `~` alone, and [resolve_emphasis] then
+//! pairs up the runs that can open and close emphasis. Strikethrough is resolved the same way,
+//! from runs of exactly two `~`, as in GitHub Flavored Markdown.
+
+use crate::*;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+pub(crate) enum InlineToken<'a> {
+    Inline(MarkdownInline<'a>),
+    Delimiter(DelimiterRun<'a>),
+    /// A char that was escaped with a `\`, which is always plaintext.
+    Escaped(&'a str),
+}
+
+/// A run of `*`, `_` or `~` chars, eg: the `**` in `**bold**`.
+#[derive(Clone, Copy)]
+pub(crate) struct DelimiterRun<'a> {
+    /// The chars of the run that have not been used to open or close emphasis yet.
+    run: &'a str,
+    /// The length of the run in the input, which the "rule of 3" looks at.
+    length: usize,
+    can_open: bool,
+    can_close: bool,
+}
+
+impl<'a> DelimiterRun<'a> {
+    /// `before` and `after` are the chars around the run, or `None` at the start or end of a line.
+    pub(crate) fn new(run: &'a str, before: Option<char>, after: Option<char>) -> Self {
+        let whitespace = |it: Option<char>| it.is_none_or(char::is_whitespace);
+        let punctuation = |it: Option<char>| it.is_some_and(is_punctuation);
+        let left_flanking = !whitespace(after)
+            && (!punctuation(after) || whitespace(before) || punctuation(before));
+        let right_flanking = !whitespace(before)
+            && (!punctuation(before) || whitespace(after) || punctuation(after));
+        let (can_open, can_close) = if run.starts_with('_') {
+            // `_` can't start or end emphasis inside a word, eg: `snake_case_name`.
+            (
+                left_flanking && (!right_flanking || punctuation(before)),
+                right_flanking && (!left_flanking || punctuation(after)),
+            )
+        } else if run.starts_with('~') && run.len() != 2 {
+            (false, false)
+        } else {
+            (left_flanking, right_flanking)
+        };
+        DelimiterRun {
+            run,
+            length: run.len(),
+            can_open,
+            can_close,
+        }
+    }
+
+    /// The "rule of 3": when one of the runs can both open and close, they only match if the sum
+    /// of their lengths is not a multiple of 3, unless both lengths are.
+    fn can_match(&self, closer: &DelimiterRun) -> bool {
+        self.can_open
+            && !self.run.is_empty()
+            && self.run[..1] == closer.run[..1]
+            && !((self.can_close || closer.can_open)
+                && (self.length + closer.length).is_multiple_of(3)
+                && !(self.length.is_multiple_of(3) && closer.length.is_multiple_of(3)))
+    }
+}
+
+/// Wraps text in emphasis, eg: [MarkdownInline::Bold].
+type Emphasis<'a> = fn(MarkdownText<'a>) -> MarkdownInline<'a>;
+
+/// ASCII punctuation, and any other char that is not a letter, a digit or whitespace.
+fn is_punctuation(it: char) -> bool {
+    it.is_ascii_punctuation() || (!it.is_ascii() && !it.is_alphanumeric() && !it.is_whitespace())
+}
+
+/// Turns the delimiter runs in `tokens` into [MarkdownInline::Italic], [MarkdownInline::Bold]
+/// and [MarkdownInline::Strikethrough], and the runs that are left over into plaintext. All the
+/// plaintext in `tokens` must be borrowed from `source`, so that adjacent plaintext can be joined
+/// without allocating. Emphasis that would be nested too deeply is kept as plaintext, see
+/// [constants::MAX_NESTING_DEPTH].
+pub(crate) fn resolve_emphasis<'a>(
+    source: &'a str,
+    mut tokens: Vec<InlineToken<'a>>,
+) -> MarkdownText<'a> {
+    // The emphasis that each token opens, and the delimiters that each token closes emphasis
+    // with, from the inside out.
+    let mut opened = vec![vec![]; tokens.len()];
+    let mut closed = vec![vec![]; tokens.len()];
+    // The runs that may still open emphasis, by index in `tokens`.
+    let mut openers: Vec<usize> = vec![];
+    // The openers before these indexes are known not to match a closer of the same kind, see
+    // <https://spec.commonmark.org/0.30/#process-emphasis>.
+    let mut openers_bottom = HashMap::new();
+    for closer in 0..tokens.len() {
+        while let InlineToken::Delimiter(run) = tokens[closer] {
+            if !run.can_close || run.run.is_empty() {
+                break;
+            }
+            let kind = (&run.run[..1], run.can_open, run.length % 3);
+            let bottom = openers_bottom.get(&kind).copied().unwrap_or(0);
+            let found = (0..openers.len())
+                .rev()
+                .take_while(|it| openers[*it] >= bottom)
+                .find(|it| can_match(&tokens[openers[*it]], &run));
+            let found = match found {
+                Some(it) => it,
+                None => {
+                    openers_bottom.insert(kind, closer);
+                    break;
+                }
+            };
+
+            let opener = openers[found];
+            let used = match &tokens[opener] {
+                InlineToken::Delimiter(it) if it.run.len() >= 2 && run.run.len() >= 2 => 2,
+                _ => 1,
+            };
+            let emphasis: Emphasis<'a> = match used {
+                _ if run.run.starts_with('~') => MarkdownInline::Strikethrough,
+                2 => MarkdownInline::Bold,
+                _ => MarkdownInline::Italic,
+            };
+            if let InlineToken::Delimiter(it) = &mut tokens[opener] {
+                let (rest, delimiters) = it.run.split_at(it.run.len() - used);
+                opened[opener].push((emphasis, delimiters));
+                it.run = rest;
+            }
+            if let InlineToken::Delimiter(it) = &mut tokens[closer] {
+                let (delimiters, rest) = it.run.split_at(used);
+                closed[closer].push(delimiters);
+                it.run = rest;
+            }
+            // The runs between the two can't open emphasis around the new one.
+            openers.truncate(found + 1);
+            if is_used_up(&tokens[opener]) {
+                openers.pop();
+            }
+        }
+        if let InlineToken::Delimiter(it) = &tokens[closer] {
+            if it.can_open && !it.run.is_empty() {
+                openers.push(closer);
+            }
+        }
+    }
+
+    // The emphasis that is open, with the text before it, or `None` if it is kept as plaintext.
+    let mut outer: Vec<Option<(Emphasis<'a>, MarkdownText<'a>)>> = vec![];
+    let mut depth = 0;
+    let mut text = vec![];
+    for ((token, opened), closed) in tokens.into_iter().zip(opened).zip(closed) {
+        for delimiters in closed {
+            match outer.pop() {
+                Some(Some((emphasis, before))) => {
+                    let children = std::mem::replace(&mut text, before);
+                    text.push(emphasis(children));
+                    depth -= 1;
+                }
+                _ => push_plaintext(source, &mut text, delimiters.into()),
+            }
+        }
+        push_token(source, &mut text, token);
+        for (emphasis, delimiters) in opened.into_iter().rev() {
+            if depth < constants::MAX_NESTING_DEPTH {
+                outer.push(Some((emphasis, std::mem::take(&mut text))));
+                depth += 1;
+            } else {
+                push_plaintext(source, &mut text, delimiters.into());
+                outer.push(None);
+            }
+        }
+    }
+    text
+}
+
+/// Whether `opener` is a delimiter run that can open emphasis that `closer` closes.
+fn can_match(opener: &InlineToken, closer: &DelimiterRun) -> bool {
+    matches!(opener, InlineToken::Delimiter(it) if it.can_match(closer))
+}
+
+fn is_used_up(token: &InlineToken) -> bool {
+    matches!(token, InlineToken::Delimiter(it) if it.run.is_empty())
+}
+
+/// Appends `token` to `text`, with a delimiter run that is left over as plaintext.
+fn push_token<'a>(source: &'a str, text: &mut MarkdownText<'a>, token: InlineToken<'a>) {
+    match token {
+        InlineToken::Inline(MarkdownInline::Plaintext(it)) => push_plaintext(source, text, it),
+        InlineToken::Inline(inline) => text.push(inline),
+        InlineToken::Delimiter(it) if it.run.is_empty() => {}
+        InlineToken::Delimiter(it) => push_plaintext(source, text, it.run.into()),
+        InlineToken::Escaped(it) => push_plaintext(source, text, it.into()),
+    }
+}
+
+/// Appends `plaintext` to `text`, joining it with the plaintext at the end of `text` if there is
+/// one. Slices of `source` that are next to each other are joined without allocating.
+fn push_plaintext<'a>(source: &'a str, text: &mut MarkdownText<'a>, plaintext: Cow<'a, str>) {
+    let last = match text.last_mut() {
+        Some(MarkdownInline::Plaintext(last)) => last,
+        _ => return text.push(MarkdownInline::Plaintext(plaintext)),
+    };
+    if let (Cow::Borrowed(left), Cow::Borrowed(right)) = (&*last, &plaintext) {
+        if let (Some(start), Some(middle)) = (offset_in(source, left), offset_in(source, right)) {
+            if start + left.len() == middle {
+                *last = Cow::Borrowed(&source[start..middle + right.len()]);
+                return;
+            }
+        }
+    }
+    last.to_mut().push_str(&plaintext);
+}
+
+/// Returns the offset of `slice` in `source`, if it is a slice of `source`.
+fn offset_in(source: &str, slice: &str) -> Option<usize> {
+    let offset = (slice.as_ptr() as usize).checked_sub(source.as_ptr() as usize)?;
+    if offset + slice.len() <= source.len() && source.is_char_boundary(offset) {
+        Some(offset)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delimiter_run_flanking() {
+        let run = |before, after| {
+            let it = DelimiterRun::new("*", before, after);
+            (it.can_open, it.can_close)
+        };
+        assert_eq!(run(None, Some('a')), (true, false));
+        assert_eq!(run(Some('a'), None), (false, true));
+        assert_eq!(run(Some('a'), Some('b')), (true, true));
+        assert_eq!(run(Some(' '), Some(' ')), (false, false));
+        assert_eq!(run(Some('a'), Some('"')), (false, true));
+        assert_eq!(run(Some(' '), Some('"')), (true, false));
+
+        let run = |before, after| {
+            let it = DelimiterRun::new("_", before, after);
+            (it.can_open, it.can_close)
+        };
+        assert_eq!(run(None, Some('a')), (true, false));
+        assert_eq!(run(Some('a'), Some('b')), (false, false));
+        assert_eq!(run(Some('"'), Some('b')), (true, false));
+        assert_eq!(run(Some('a'), Some('"')), (false, true));
+    }
+
+    #[test]
+    fn test_push_plaintext() {
+        let source = "snake_case";
+        let mut text = vec![];
+        push_plaintext(source, &mut text, Cow::Borrowed(&source[..5]));
+        push_plaintext(source, &mut text, Cow::Borrowed(&source[5..6]));
+        push_plaintext(source, &mut text, Cow::Borrowed(&source[6..]));
+        assert_eq!(text, vec![MarkdownInline::Plaintext("snake_case".into())]);
+        assert!(matches!(
+            &text[0],
+            MarkdownInline::Plaintext(Cow::Borrowed(_))
+        ));
+
+        push_plaintext(source, &mut text, Cow::Borrowed(&source[..1]));
+        assert_eq!(text, vec![MarkdownInline::Plaintext("snake_cases".into())]);
+        assert!(matches!(&text[0], MarkdownInline::Plaintext(Cow::Owned(_))));
+    }
+
+    /// Examples from <https://spec.commonmark.org/0.30/#emphasis-and-strong-emphasis>, with
+    /// `<em>` and `<strong>` rendered as `<i>` and `<b>`.
+    #[test]
+    fn test_commonmark_emphasis_examples() {
+        let examples = [
+            ("*foo bar*", "<i>foo bar</i>"),
+            ("a * foo bar*", "a * foo bar*"),
+            ("a*\"foo\"*", "a*&quot;foo&quot;*"),
+            ("foo*bar*", "foo<i>bar</i>"),
+            ("5*6*78", "5<i>6</i>78"),
+            ("_foo bar_", "<i>foo bar</i>"),
+            ("_ foo bar_", "_ foo bar_"),
+            ("a_\"foo\"_", "a_&quot;foo&quot;_"),
+            ("foo_bar_", "foo_bar_"),
+            ("5_6_78", "5_6_78"),
+            ("пристаням_стремятся_", "пристаням_стремятся_"),
+            ("aa_\"bb\"_cc", "aa_&quot;bb&quot;_cc"),
+            ("foo-_(bar)_", "foo-<i>(bar)</i>"),
+            ("_foo*", "_foo*"),
+            ("*foo bar *", "*foo bar *"),
+            ("*(*foo)", "*(*foo)"),
+            ("*(*foo*)*", "<i>(<i>foo</i>)</i>"),
+            ("*foo*bar", "<i>foo</i>bar"),
+            ("_foo bar _", "_foo bar _"),
+            ("_(_foo)", "_(_foo)"),
+            ("_(_foo_)_", "<i>(<i>foo</i>)</i>"),
+            ("_foo_bar", "_foo_bar"),
+            ("_пристаням_стремятся", "_пристаням_стремятся"),
+            ("_foo_bar_baz_", "<i>foo_bar_baz</i>"),
+            ("_(bar)_.", "<i>(bar)</i>."),
+            ("**foo bar**", "<b>foo bar</b>"),
+            ("** foo bar**", "** foo bar**"),
+            ("a**\"foo\"**", "a**&quot;foo&quot;**"),
+            ("foo**bar**", "foo<b>bar</b>"),
+            ("__foo bar__", "<b>foo bar</b>"),
+            ("__ foo bar__", "__ foo bar__"),
+            ("a__\"foo\"__", "a__&quot;foo&quot;__"),
+            ("foo__bar__", "foo__bar__"),
+            ("5__6__78", "5__6__78"),
+            ("__foo, __bar__, baz__", "<b>foo, <b>bar</b>, baz</b>"),
+            ("foo-__(bar)__", "foo-<b>(bar)</b>"),
+            ("**foo bar **", "**foo bar **"),
+            ("**(**foo)", "**(**foo)"),
+            ("*(**foo**)*", "<i>(<b>foo</b>)</i>"),
+            (
+                "**foo \"*bar*\" foo**",
+                "<b>foo &quot;<i>bar</i>&quot; foo</b>",
+            ),
+            ("**foo**bar", "<b>foo</b>bar"),
+            ("__foo bar __", "__foo bar __"),
+            ("__(__foo)", "__(__foo)"),
+            ("_(__foo__)_", "<i>(<b>foo</b>)</i>"),
+            ("__foo__bar", "__foo__bar"),
+            ("__foo__bar__baz__", "<b>foo__bar__baz</b>"),
+            ("__(bar)__.", "<b>(bar)</b>."),
+            ("*foo [bar](/url)*", "<i>foo <a href=\"/url\">bar</a></i>"),
+            ("_foo __bar__ baz_", "<i>foo <b>bar</b> baz</i>"),
+            ("_foo _bar_ baz_", "<i>foo <i>bar</i> baz</i>"),
+            ("__foo_ bar_", "<i><i>foo</i> bar</i>"),
+            ("*foo *bar**", "<i>foo <i>bar</i></i>"),
+            ("*foo **bar** baz*", "<i>foo <b>bar</b> baz</i>"),
+            ("*foo**bar**baz*", "<i>foo<b>bar</b>baz</i>"),
+            ("*foo**bar*", "<i>foo**bar</i>"),
+            ("***foo** bar*", "<i><b>foo</b> bar</i>"),
+            ("*foo **bar***", "<i>foo <b>bar</b></i>"),
+            ("*foo**bar***", "<i>foo<b>bar</b></i>"),
+            ("foo***bar***baz", "foo<i><b>bar</b></i>baz"),
+            (
+                "foo******bar*********baz",
+                "foo<b><b><b>bar</b></b></b>***baz",
+            ),
+            (
+                "*foo **bar *baz* bim** bop*",
+                "<i>foo <b>bar <i>baz</i> bim</b> bop</i>",
+            ),
+            (
+                "*foo [*bar*](/url)*",
+                "<i>foo <a href=\"/url\"><i>bar</i></a></i>",
+            ),
+            ("** is not an empty emphasis", "** is not an empty emphasis"),
+            (
+                "**** is not an empty strong emphasis",
+                "**** is not an empty strong emphasis",
+            ),
+            ("foo ***", "foo ***"),
+            ("foo *****", "foo *****"),
+            ("**foo*", "*<i>foo</i>"),
+            ("*foo**", "<i>foo</i>*"),
+            ("***foo**", "*<b>foo</b>"),
+            ("****foo*", "***<i>foo</i>"),
+            ("**foo***", "<b>foo</b>*"),
+            ("*foo****", "<i>foo</i>***"),
+            ("***foo***", "<i><b>foo</b></i>"),
+            ("_____foo_____", "<i><b><b>foo</b></b></i>"),
+            ("*foo _bar* baz_", "<i>foo _bar</i> baz_"),
+            (
+                "*foo __bar *baz bim__ bam*",
+                "<i>foo <b>bar *baz bim</b> bam</i>",
+            ),
+            ("**foo **bar baz**", "**foo <b>bar baz</b>"),
+            ("*foo *bar baz*", "*foo <i>bar baz</i>"),
+            ("*[bar*](/url)", "*<a href=\"/url\">bar*</a>"),
+            ("*a `*`*", "<i>a <code>*</code></i>"),
+            ("_a `_`_", "<i>a <code>_</code></i>"),
+            ("2 * 3 * 4", "2 * 3 * 4"),
+            ("snake_case_name", "snake_case_name"),
+            ("**a*b**", "<b>a*b</b>"),
+        ];
+        for (markdown, html) in examples {
+            assert_eq!(
+                render_markdown(markdown),
+                format!("<p>{html}</p>"),
+                "{markdown}"
+            );
+        }
+        assert_eq!(render_markdown("*foo\nbar*"), "<p><i>foo\nbar</i></p>");
+    }
+
+    #[test]
+    fn test_deeply_nested_emphasis() {
+        let depth = constants::MAX_NESTING_DEPTH;
+        let delimiters = "**".repeat(1000 - depth);
+        assert_eq!(
+            render_markdown(&format!("{}a{}", "**".repeat(1000), "**".repeat(1000))),
+            format!(
+                "<p>{}{delimiters}a{delimiters}{}</p>",
+                "<b>".repeat(depth),
+                "</b>".repeat(depth)
+            )
+        );
+    }
+}
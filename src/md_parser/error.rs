@@ -0,0 +1,111 @@
+use std::fmt;
+
+/// Returned by [crate::try_parse_markdown] and [crate::try_render_markdown] when some of the input
+/// is left unparsed. Any line can be read as a paragraph, and a codeblock that is never closed
+/// runs to the end of the input, so this points at a bug in the parser rather than in the input.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub position: Position,
+}
+
+/// A location in the input. `line` and `column` start at 1, `column` counts chars (not bytes).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl ParseError {
+    /// Builds the error for a parse of `input` that stopped at `rest`, which must be a suffix of
+    /// `input`.
+    pub(crate) fn new(input: &str, rest: &str) -> Self {
+        ParseError {
+            position: Position::new(input, input.len() - rest.len()),
+        }
+    }
+}
+
+impl Position {
+    /// Lines may end with `\n`, `\r\n` or a lone `\r`.
+    pub(crate) fn new(input: &str, offset: usize) -> Self {
+        let before = &input[..offset];
+        let (mut line, mut line_start) = (1, 0);
+        for (index, byte) in before.bytes().enumerate() {
+            if byte != b'\n' && byte != b'\r' {
+                continue;
+            }
+            let is_crlf = byte == b'\n' && index > 0 && before.as_bytes()[index - 1] == b'\r';
+            if !is_crlf {
+                line += 1;
+            }
+            line_start = index + 1;
+        }
+        Position {
+            offset,
+            line,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Position { line, column, .. } = self.position;
+        write!(
+            f,
+            "could not parse the input at line {line}, column {column}"
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position() {
+        assert_eq!(
+            Position::new("abc", 0),
+            Position {
+                offset: 0,
+                line: 1,
+                column: 1
+            }
+        );
+        assert_eq!(
+            Position::new("ab\ncd\nef", 7),
+            Position {
+                offset: 7,
+                line: 3,
+                column: 2
+            }
+        );
+        assert_eq!(
+            Position::new("ab\r\ncd\ref", 8),
+            Position {
+                offset: 8,
+                line: 3,
+                column: 2
+            }
+        );
+        assert_eq!(
+            Position::new("🦀🦀\n🦀x", 13),
+            Position {
+                offset: 13,
+                line: 2,
+                column: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            ParseError::new("a\n~~~~\nb", "b").to_string(),
+            "could not parse the input at line 3, column 1"
+        );
+    }
+}
@@ -0,0 +1,368 @@
+use crate::*;
+use std::collections::HashMap;
+
+/// A footnote that can't be rendered as one, see [find_footnote_problems]. Holds its label.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FootnoteProblem {
+    /// A reference like `[^1]` to a footnote that is not defined. It is rendered as text.
+    Undefined(String),
+    /// A footnote that is defined but never referenced. It is left out.
+    Unreferenced(String),
+}
+
+/// Numbers the footnotes in `md` in the order they are first referenced, and moves their
+/// definitions into a [Markdown::Footnotes] at the end of `md`. References inside footnotes are
+/// numbered after the ones in the rest of the document. If a label is defined more than once, the
+/// first definition is used.
+pub fn resolve_footnotes(md: Vec<Markdown<'_>>) -> Vec<Markdown<'_>> {
+    let mut definitions = vec![];
+    let mut md = take_footnote_definitions(md, &mut definitions);
+    if definitions.is_empty() {
+        return md;
+    }
+    let mut numbering = Numbering {
+        references: vec![0; definitions.len()],
+        ..Numbering::default()
+    };
+    for (index, definition) in definitions.iter().enumerate() {
+        numbering
+            .labels
+            .entry(normalize_link_label(&definition.label))
+            .or_insert(index);
+    }
+    number_blocks(&mut md, &mut numbering);
+    let mut position = 0;
+    while let Some(&index) = numbering.order.get(position) {
+        let mut blocks = std::mem::take(&mut definitions[index].blocks);
+        number_blocks(&mut blocks, &mut numbering);
+        definitions[index].blocks = blocks;
+        position += 1;
+    }
+
+    let mut definitions = definitions.into_iter().map(Some).collect::<Vec<_>>();
+    let mut footnotes = vec![];
+    for &index in &numbering.order {
+        if let Some(FootnoteDefinition { label, blocks }) = definitions[index].take() {
+            footnotes.push(Footnote {
+                label,
+                blocks,
+                references: numbering.references[index],
+            });
+        }
+    }
+    for (index, definition) in definitions.into_iter().enumerate() {
+        match definition {
+            Some(FootnoteDefinition { label, blocks })
+                if numbering.labels[&normalize_link_label(&label)] == index =>
+            {
+                footnotes.push(Footnote {
+                    label,
+                    blocks,
+                    references: 0,
+                })
+            }
+            _ => {}
+        }
+    }
+    md.push(Markdown::Footnotes(footnotes));
+    md
+}
+
+/// Finds the references to footnotes that are not defined, in document order, followed by the
+/// footnotes that are never referenced. `md` must have been resolved by [resolve_footnotes]. Each
+/// label is reported once.
+pub fn find_footnote_problems(md: &[Markdown]) -> Vec<FootnoteProblem> {
+    let mut problems = vec![];
+    find_problems_in_blocks(md, &mut problems);
+    problems
+}
+
+#[derive(Default)]
+struct Numbering {
+    /// The index of the definition of each normalized label.
+    labels: HashMap<String, usize>,
+    /// The indexes of the definitions that are referenced, in the order they are first
+    /// referenced.
+    order: Vec<usize>,
+    /// How many times each definition is referenced.
+    references: Vec<usize>,
+}
+
+impl Numbering {
+    fn number(&mut self, reference: &mut FootnoteReference) {
+        let index = match self.labels.get(&normalize_link_label(&reference.label)) {
+            Some(index) => *index,
+            None => {
+                reference.number = None;
+                reference.index = 0;
+                return;
+            }
+        };
+        let position = match self.order.iter().position(|it| *it == index) {
+            Some(position) => position,
+            None => {
+                self.order.push(index);
+                self.order.len() - 1
+            }
+        };
+        self.references[index] += 1;
+        reference.number = Some(position + 1);
+        reference.index = self.references[index];
+    }
+}
+
+fn take_footnote_definitions<'a>(
+    md: Vec<Markdown<'a>>,
+    definitions: &mut Vec<FootnoteDefinition<'a>>,
+) -> Vec<Markdown<'a>> {
+    let mut blocks = vec![];
+    for bit in md {
+        match bit {
+            Markdown::FootnoteDefinition(definition) => definitions.push(definition),
+            Markdown::UnorderedList(items) => {
+                blocks.push(Markdown::UnorderedList(take_from_items(items, definitions)))
+            }
+            Markdown::OrderedList(items) => {
+                blocks.push(Markdown::OrderedList(take_from_items(items, definitions)))
+            }
+            Markdown::BlockQuote(inner) => blocks.push(Markdown::BlockQuote(
+                take_footnote_definitions(inner, definitions),
+            )),
+            bit => blocks.push(bit),
+        }
+    }
+    blocks
+}
+
+fn take_from_items<'a>(
+    items: Vec<ListItem<'a>>,
+    definitions: &mut Vec<FootnoteDefinition<'a>>,
+) -> Vec<ListItem<'a>> {
+    items
+        .into_iter()
+        .map(|item| ListItem {
+            checked: item.checked,
+            blocks: take_footnote_definitions(item.blocks, definitions),
+        })
+        .collect()
+}
+
+fn number_blocks(md: &mut [Markdown], numbering: &mut Numbering) {
+    for bit in md {
+        match bit {
+            Markdown::Heading(_, text) | Markdown::Paragraph(text) => number_text(text, numbering),
+            Markdown::OrderedList(items) | Markdown::UnorderedList(items) => {
+                for item in items {
+                    number_blocks(&mut item.blocks, numbering);
+                }
+            }
+            Markdown::BlockQuote(blocks)
+            | Markdown::FootnoteDefinition(FootnoteDefinition { blocks, .. }) => {
+                number_blocks(blocks, numbering)
+            }
+            Markdown::Table(table) => {
+                for cell in table
+                    .header
+                    .iter_mut()
+                    .chain(table.rows.iter_mut().flatten())
+                {
+                    number_text(cell, numbering);
+                }
+            }
+            Markdown::Codeblock(..)
+            | Markdown::ThematicBreak
+            | Markdown::LinkDefinition(_)
+            | Markdown::Footnotes(_)
+            | Markdown::FrontMatter(_) => {}
+        }
+    }
+}
+
+fn number_text(text: &mut MarkdownText, numbering: &mut Numbering) {
+    for part in text {
+        match part {
+            MarkdownInline::Bold(text)
+            | MarkdownInline::Italic(text)
+            | MarkdownInline::Strikethrough(text)
+            | MarkdownInline::Link(Link { text, .. })
+            | MarkdownInline::LinkReference((text, ..)) => number_text(text, numbering),
+            MarkdownInline::FootnoteReference(reference) => numbering.number(reference),
+            _ => {}
+        }
+    }
+}
+
+fn find_problems_in_blocks(md: &[Markdown], problems: &mut Vec<FootnoteProblem>) {
+    for bit in md {
+        match bit {
+            Markdown::Heading(_, text) | Markdown::Paragraph(text) => {
+                find_problems_in_text(text, problems)
+            }
+            Markdown::OrderedList(items) | Markdown::UnorderedList(items) => {
+                for item in items {
+                    find_problems_in_blocks(&item.blocks, problems);
+                }
+            }
+            Markdown::BlockQuote(blocks)
+            | Markdown::FootnoteDefinition(FootnoteDefinition { blocks, .. }) => {
+                find_problems_in_blocks(blocks, problems)
+            }
+            Markdown::Table(table) => {
+                for cell in table.header.iter().chain(table.rows.iter().flatten()) {
+                    find_problems_in_text(cell, problems);
+                }
+            }
+            Markdown::Footnotes(footnotes) => {
+                for footnote in footnotes {
+                    if footnote.references > 0 {
+                        find_problems_in_blocks(&footnote.blocks, problems);
+                    }
+                }
+                for footnote in footnotes.iter().filter(|it| it.references == 0) {
+                    problems.push(FootnoteProblem::Unreferenced(footnote.label.to_string()));
+                }
+            }
+            Markdown::Codeblock(..)
+            | Markdown::ThematicBreak
+            | Markdown::LinkDefinition(_)
+            | Markdown::FrontMatter(_) => {}
+        }
+    }
+}
+
+fn find_problems_in_text(text: &MarkdownText, problems: &mut Vec<FootnoteProblem>) {
+    for part in text {
+        match part {
+            MarkdownInline::Bold(text)
+            | MarkdownInline::Italic(text)
+            | MarkdownInline::Strikethrough(text)
+            | MarkdownInline::Link(Link { text, .. })
+            | MarkdownInline::LinkReference((text, ..)) => find_problems_in_text(text, problems),
+            MarkdownInline::FootnoteReference(FootnoteReference {
+                label,
+                number: None,
+                ..
+            }) => {
+                let problem = FootnoteProblem::Undefined(label.to_string());
+                if !problems.contains(&problem) {
+                    problems.push(problem);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_footnotes() {
+        assert_eq!(
+            try_parse_markdown(concat!(
+                "A[^b] B[^A] C[^b] D[^x].\n",
+                "\n",
+                "[^a]: First.\n",
+                "[^b]: Second[^c].\n",
+                "\n",
+                "    More.\n",
+                "[^c]: Third.\n",
+                "[^d]: Unused.\n",
+                "[^A]: Again.\n",
+            )),
+            Ok(vec![
+                Markdown::Paragraph(vec![
+                    MarkdownInline::Plaintext("A".into()),
+                    MarkdownInline::FootnoteReference(FootnoteReference {
+                        label: "b".into(),
+                        number: Some(1),
+                        index: 1
+                    }),
+                    MarkdownInline::Plaintext(" B".into()),
+                    MarkdownInline::FootnoteReference(FootnoteReference {
+                        label: "A".into(),
+                        number: Some(2),
+                        index: 1
+                    }),
+                    MarkdownInline::Plaintext(" C".into()),
+                    MarkdownInline::FootnoteReference(FootnoteReference {
+                        label: "b".into(),
+                        number: Some(1),
+                        index: 2
+                    }),
+                    MarkdownInline::Plaintext(" D".into()),
+                    MarkdownInline::FootnoteReference(FootnoteReference {
+                        label: "x".into(),
+                        number: None,
+                        index: 0
+                    }),
+                    MarkdownInline::Plaintext(".".into()),
+                ]),
+                Markdown::Footnotes(vec![
+                    Footnote {
+                        label: "b".into(),
+                        blocks: vec![
+                            Markdown::Paragraph(vec![
+                                MarkdownInline::Plaintext("Second".into()),
+                                MarkdownInline::FootnoteReference(FootnoteReference {
+                                    label: "c".into(),
+                                    number: Some(3),
+                                    index: 1
+                                }),
+                                MarkdownInline::Plaintext(".".into()),
+                            ]),
+                            Markdown::Paragraph(vec![MarkdownInline::Plaintext("More.".into())]),
+                        ],
+                        references: 2
+                    },
+                    Footnote {
+                        label: "a".into(),
+                        blocks: vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                            "First.".into()
+                        )])],
+                        references: 1
+                    },
+                    Footnote {
+                        label: "c".into(),
+                        blocks: vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                            "Third.".into()
+                        )])],
+                        references: 1
+                    },
+                    Footnote {
+                        label: "d".into(),
+                        blocks: vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                            "Unused.".into()
+                        )])],
+                        references: 0
+                    },
+                ]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_find_footnote_problems() {
+        let md = try_parse_markdown("a[^1] b[^2] c[^2]\n\n[^1]: One.\n[^3]: Three.\n").unwrap();
+        assert_eq!(
+            find_footnote_problems(&md),
+            vec![
+                FootnoteProblem::Undefined(String::from("2")),
+                FootnoteProblem::Unreferenced(String::from("3")),
+            ]
+        );
+        assert_eq!(
+            render_markdown("a[^1] b[^1] c[^2]\n\n[^1]: One.\n"),
+            String::from(concat!(
+                "<p>a<sup class=\"footnote-ref\"><a href=\"#fn-1\" id=\"fnref-1\">1</a></sup> ",
+                "b<sup class=\"footnote-ref\"><a href=\"#fn-1\" id=\"fnref-1-2\">1</a></sup> ",
+                "c[^2]</p>",
+                "<section class=\"footnotes\"><ol><li id=\"fn-1\"><p>One. ",
+                "<a href=\"#fnref-1\" class=\"footnote-backref\">↩</a> ",
+                "<a href=\"#fnref-1-2\" class=\"footnote-backref\">↩<sup>2</sup></a>",
+                "</p></li></ol></section>",
+            ))
+        );
+    }
+}
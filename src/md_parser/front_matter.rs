@@ -0,0 +1,125 @@
+use crate::*;
+use std::collections::HashMap;
+
+/// Reads the `key: value` (YAML) or `key = value` (TOML) lines of front matter. This is not a full
+/// YAML or TOML parser: values are kept as text, with the quotes around them removed, and nested
+/// values like indented YAML lines and list items are skipped. Keys in a TOML table are prefixed
+/// with its name, eg: `author.name` for `name` under `[author]`. Lines can end with `\n`, `\r\n`
+/// or a lone `\r`.
+pub fn parse_front_matter_fields(format: FrontMatterFormat, raw: &str) -> HashMap<String, String> {
+    let separator = match format {
+        FrontMatterFormat::Yaml => ':',
+        FrontMatterFormat::Toml => '=',
+    };
+    let mut table = String::new();
+    let mut fields = HashMap::new();
+    for line in normalize_line_endings(raw).lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || line.starts_with([' ', '\t']) {
+            continue;
+        }
+        if format == FrontMatterFormat::Toml && trimmed.starts_with('[') {
+            table = trimmed.trim_matches(['[', ']']).trim().to_string();
+            continue;
+        }
+        if format == FrontMatterFormat::Yaml && trimmed.starts_with('-') {
+            continue;
+        }
+        let (key, value) = match trimmed.split_once(separator) {
+            Some((key, value)) if !key.trim().is_empty() => (unquote(key.trim()), value.trim()),
+            _ => continue,
+        };
+        let key = match table.as_str() {
+            "" => key.to_string(),
+            table => format!("{table}.{key}"),
+        };
+        fields.insert(key, unquote(value).to_string());
+    }
+    fields
+}
+
+/// Removes the `"` or `'` around `text`, if there are any.
+fn unquote(text: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = text
+            .strip_prefix(quote)
+            .and_then(|it| it.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_front_matter_fields() {
+        assert_eq!(
+            parse_front_matter_fields(
+                FrontMatterFormat::Yaml,
+                concat!(
+                    "title: \"Hello: world\"\n",
+                    "# a comment\n",
+                    "url: https://example.com\n",
+                    "tags:\n",
+                    "  - rust\n",
+                    "- markdown\n",
+                    "draft: false\n",
+                )
+            ),
+            HashMap::from([
+                (String::from("title"), String::from("Hello: world")),
+                (String::from("url"), String::from("https://example.com")),
+                (String::from("tags"), String::new()),
+                (String::from("draft"), String::from("false")),
+            ])
+        );
+        assert_eq!(
+            parse_front_matter_fields(
+                FrontMatterFormat::Toml,
+                concat!(
+                    "title = 'Hello'\n",
+                    "tags = [\"rust\", \"markdown\"]\n",
+                    "\n",
+                    "[author]\n",
+                    "name = \"Me\"\n",
+                )
+            ),
+            HashMap::from([
+                (String::from("title"), String::from("Hello")),
+                (
+                    String::from("tags"),
+                    String::from("[\"rust\", \"markdown\"]")
+                ),
+                (String::from("author.name"), String::from("Me")),
+            ])
+        );
+        assert_eq!(
+            parse_front_matter_fields(FrontMatterFormat::Yaml, "a: 1\rb: 2\r\nc: 3\r"),
+            HashMap::from([
+                (String::from("a"), String::from("1")),
+                (String::from("b"), String::from("2")),
+                (String::from("c"), String::from("3")),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_front_matter_is_not_rendered() {
+        assert_eq!(
+            render_markdown("---\ntitle: Hello\n---\n# Hello\n"),
+            String::from("<h1>Hello</h1>")
+        );
+        assert_eq!(
+            render_markdown("+++\r\ntitle = \"Hello\"\r\n+++\r\nText\r\n"),
+            String::from("<p>Text</p>")
+        );
+        assert_eq!(
+            render_markdown("Text\n\n---\na: b\n---\n"),
+            String::from("<p>Text</p><hr /><h2>a: b</h2>")
+        );
+    }
+}
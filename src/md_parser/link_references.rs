@@ -0,0 +1,233 @@
+use crate::*;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// The URL and title of each [LinkDefinition], by normalized label.
+type LinkDefinitions<'a> = HashMap<String, (Cow<'a, str>, Option<Cow<'a, str>>)>;
+
+/// Replaces every [MarkdownInline::LinkReference] in `md` with a link to the [LinkDefinition]
+/// that it refers to, or with its source text if there is none, and removes the definitions. The
+/// definitions can be anywhere in the document, eg: at the bottom or in a blockquote. If a label
+/// is defined more than once, the first definition is used.
+pub fn resolve_link_references(md: Vec<Markdown<'_>>) -> Vec<Markdown<'_>> {
+    let mut definitions = LinkDefinitions::new();
+    collect_link_definitions(&md, &mut definitions);
+    resolve_blocks(md, &definitions)
+}
+
+/// Labels are matched case-insensitively, and runs of whitespace in them are treated as a single
+/// space, eg: `[Foo  Bar]` refers to `[foo bar]: /url`.
+pub fn normalize_link_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn collect_link_definitions<'a>(md: &[Markdown<'a>], definitions: &mut LinkDefinitions<'a>) {
+    for bit in md {
+        match bit {
+            Markdown::LinkDefinition(LinkDefinition { label, url, title }) => {
+                definitions
+                    .entry(normalize_link_label(label))
+                    .or_insert_with(|| (url.clone(), title.clone()));
+            }
+            Markdown::OrderedList(items) | Markdown::UnorderedList(items) => {
+                for item in items {
+                    collect_link_definitions(&item.blocks, definitions);
+                }
+            }
+            Markdown::BlockQuote(blocks)
+            | Markdown::FootnoteDefinition(FootnoteDefinition { blocks, .. }) => {
+                collect_link_definitions(blocks, definitions)
+            }
+            Markdown::Footnotes(footnotes) => {
+                for footnote in footnotes {
+                    collect_link_definitions(&footnote.blocks, definitions);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn resolve_blocks<'a>(
+    md: Vec<Markdown<'a>>,
+    definitions: &LinkDefinitions<'a>,
+) -> Vec<Markdown<'a>> {
+    md.into_iter()
+        .filter(|bit| !matches!(bit, Markdown::LinkDefinition(_)))
+        .map(|bit| match bit {
+            Markdown::Heading(size, line) => {
+                Markdown::Heading(size, resolve_text(line, definitions))
+            }
+            Markdown::UnorderedList(items) => {
+                Markdown::UnorderedList(resolve_items(items, definitions))
+            }
+            Markdown::OrderedList(items) => {
+                Markdown::OrderedList(resolve_items(items, definitions))
+            }
+            Markdown::Paragraph(text) => Markdown::Paragraph(resolve_text(text, definitions)),
+            Markdown::BlockQuote(blocks) => {
+                Markdown::BlockQuote(resolve_blocks(blocks, definitions))
+            }
+            Markdown::Table(Table {
+                header,
+                alignments,
+                rows,
+            }) => {
+                let resolve_row = |row: Vec<MarkdownText<'a>>| {
+                    row.into_iter()
+                        .map(|cell| resolve_text(cell, definitions))
+                        .collect()
+                };
+                Markdown::Table(Table {
+                    header: resolve_row(header),
+                    alignments,
+                    rows: rows.into_iter().map(resolve_row).collect(),
+                })
+            }
+            Markdown::FootnoteDefinition(FootnoteDefinition { label, blocks }) => {
+                Markdown::FootnoteDefinition(FootnoteDefinition {
+                    label,
+                    blocks: resolve_blocks(blocks, definitions),
+                })
+            }
+            Markdown::Footnotes(footnotes) => Markdown::Footnotes(
+                footnotes
+                    .into_iter()
+                    .map(|footnote| Footnote {
+                        blocks: resolve_blocks(footnote.blocks, definitions),
+                        ..footnote
+                    })
+                    .collect(),
+            ),
+            Markdown::Codeblock(..)
+            | Markdown::ThematicBreak
+            | Markdown::LinkDefinition(_)
+            | Markdown::FrontMatter(_) => bit,
+        })
+        .collect()
+}
+
+fn resolve_items<'a>(
+    items: Vec<ListItem<'a>>,
+    definitions: &LinkDefinitions<'a>,
+) -> Vec<ListItem<'a>> {
+    items
+        .into_iter()
+        .map(|item| ListItem {
+            checked: item.checked,
+            blocks: resolve_blocks(item.blocks, definitions),
+        })
+        .collect()
+}
+
+fn resolve_text<'a>(text: MarkdownText<'a>, definitions: &LinkDefinitions<'a>) -> MarkdownText<'a> {
+    let mut result = vec![];
+    for part in text {
+        match part {
+            MarkdownInline::Bold(text) => {
+                result.push(MarkdownInline::Bold(resolve_text(text, definitions)))
+            }
+            MarkdownInline::Italic(text) => {
+                result.push(MarkdownInline::Italic(resolve_text(text, definitions)))
+            }
+            MarkdownInline::Strikethrough(text) => result.push(MarkdownInline::Strikethrough(
+                resolve_text(text, definitions),
+            )),
+            // Links can't contain other links, so references in their text are left as text.
+            MarkdownInline::Link(Link { text, url, title }) => {
+                result.push(MarkdownInline::Link(Link {
+                    text: resolve_text(text, &LinkDefinitions::new()),
+                    url,
+                    title,
+                }))
+            }
+            MarkdownInline::LinkReference((text, label, closing)) => {
+                match definitions.get(&normalize_link_label(&label)) {
+                    Some((url, title)) => result.push(MarkdownInline::Link(Link {
+                        text: resolve_text(text, &LinkDefinitions::new()),
+                        url: url.clone(),
+                        title: title.clone(),
+                    })),
+                    None => {
+                        push_plaintext(&mut result, "[".into());
+                        for part in resolve_text(text, definitions) {
+                            match part {
+                                MarkdownInline::Plaintext(it) => push_plaintext(&mut result, it),
+                                part => result.push(part),
+                            }
+                        }
+                        push_plaintext(&mut result, closing);
+                    }
+                }
+            }
+            MarkdownInline::Plaintext(it) => push_plaintext(&mut result, it),
+            part => result.push(part),
+        }
+    }
+    result
+}
+
+/// Appends `text`, merging it into the plaintext at the end of `result` if there is one.
+fn push_plaintext<'a>(result: &mut MarkdownText<'a>, text: Cow<'a, str>) {
+    match result.last_mut() {
+        Some(MarkdownInline::Plaintext(last)) => last.to_mut().push_str(&text),
+        _ => result.push(MarkdownInline::Plaintext(text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_link_label() {
+        assert_eq!(normalize_link_label("Foo"), "foo");
+        assert_eq!(normalize_link_label("  Foo \n\t BAR "), "foo bar");
+        assert_eq!(normalize_link_label("ÄÖÜ"), "äöü");
+    }
+
+    #[test]
+    fn test_resolve_link_references() {
+        assert_eq!(
+            try_parse_markdown(concat!(
+                "See [the docs][Docs], [docs][] and [DOCS], or [nothing].\n",
+                "\n",
+                "> [docs]: /docs \"The docs\"\n",
+                "\n",
+                "[docs]: /other\n",
+            )),
+            Ok(vec![
+                Markdown::Paragraph(vec![
+                    MarkdownInline::Plaintext("See ".into()),
+                    MarkdownInline::Link(Link {
+                        text: vec![MarkdownInline::Plaintext("the docs".into())],
+                        url: "/docs".into(),
+                        title: Some("The docs".into())
+                    }),
+                    MarkdownInline::Plaintext(", ".into()),
+                    MarkdownInline::Link(Link {
+                        text: vec![MarkdownInline::Plaintext("docs".into())],
+                        url: "/docs".into(),
+                        title: Some("The docs".into())
+                    }),
+                    MarkdownInline::Plaintext(" and ".into()),
+                    MarkdownInline::Link(Link {
+                        text: vec![MarkdownInline::Plaintext("DOCS".into())],
+                        url: "/docs".into(),
+                        title: Some("The docs".into())
+                    }),
+                    MarkdownInline::Plaintext(", or [nothing].".into()),
+                ]),
+                Markdown::BlockQuote(vec![]),
+            ])
+        );
+        assert_eq!(
+            render_markdown("[*a*][b] [c]\n\n[b]: <my url>\n"),
+            String::from("<p><a href=\"my url\"><i>a</i></a> [c]</p>")
+        );
+    }
+}
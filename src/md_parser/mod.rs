@@ -1,7 +1,21 @@
+pub mod autolinks;
+mod emphasis;
+pub mod error;
+pub mod footnotes;
+pub mod front_matter;
+pub mod link_references;
 pub mod parser;
 pub mod translator;
 pub mod types;
+pub mod url_policy;
 
+pub use autolinks::*;
+pub(crate) use emphasis::*;
+pub use error::*;
+pub use footnotes::*;
+pub use front_matter::*;
+pub use link_references::*;
 pub use parser::*;
 pub use translator::*;
 pub use types::*;
+pub use url_policy::*;
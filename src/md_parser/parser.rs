@@ -1,19 +1,51 @@
 use crate::*;
 use nom::{
-    branch::*, bytes::complete::*, character::*, combinator::*, multi::*, sequence::*, IResult,
+    branch::*,
+    bytes::complete::*,
+    character::complete::{one_of, satisfy, space0, space1},
+    character::*,
+    combinator::*,
+    multi::*,
+    sequence::*,
+    IResult,
 };
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::convert::TryFrom;
 
-/// Main entry point for the MD parsing module.
+/// Main entry point for the MD parsing module. Renders the error message if `md` can't be parsed,
+/// use [try_render_markdown] to tell the two apart.
 pub fn render_markdown(md: &str) -> String {
+    try_render_markdown(md).unwrap_or_else(|err| err.to_string())
+}
+
+pub fn try_render_markdown(md: &str) -> Result<String, ParseError> {
+    try_parse_markdown(md).map(translate)
+}
+
+/// Parses all of `md`. Unlike [parse_markdown] it is an error to leave any input unparsed,
+/// reference links are resolved, see [resolve_link_references], bare URLs are turned into links,
+/// see [find_extended_autolinks], and footnotes are numbered, see [resolve_footnotes].
+pub fn try_parse_markdown(md: &str) -> Result<Vec<Markdown<'_>>, ParseError> {
     match parse_markdown(md) {
-         Ok((_, m)) => translate(m),
-         Err(_) => String::from("Sorry, this did not seem to work! Maybe your markdown was not well formed, have you hit [Enter] after your last line?"),
-     }
+        Ok(("", m)) => {
+            let m = find_extended_autolinks(resolve_link_references(m));
+            Ok(resolve_footnotes(m))
+        }
+        Ok((rest, _)) => Err(ParseError::new(md, rest)),
+        Err(nom::Err::Error(err) | nom::Err::Failure(err)) => Err(ParseError::new(md, err.input)),
+        Err(nom::Err::Incomplete(_)) => unreachable!("complete parsers never return Incomplete"),
+    }
 }
 
 pub mod constants {
     pub const HEADING_CHAR: char = '#';
     pub const SPACE_STR: &str = " ";
+    /// How deeply container blocks (blockquotes, list items and footnote definitions) can nest,
+    /// how deeply brackets can nest in the text of a link, and how deeply emphasis can nest. The
+    /// marker of a container, the `[` of a link, or the `*` of emphasis, that would be nested any
+    /// deeper is kept as text.
+    pub const MAX_NESTING_DEPTH: usize = 16;
 }
 
 /// Skip rustfmt for this module: <https://stackoverflow.com/a/67289474/2085356>. It is cleaner
@@ -23,59 +55,539 @@ pub mod constants {
 pub mod parser_impl {
     use super::*;
 
-    pub fn parse_markdown(input: &str) -> IResult<&str, Vec<Markdown>> {
-        many0(
+    /// Parses a document, which may start with [Markdown::FrontMatter], followed by its blocks,
+    /// see [parse_blocks].
+    pub fn parse_markdown(input: &str) -> IResult<&str, Vec<Markdown<'_>>> {
+        map(
+            pair(opt(parse_front_matter), parse_blocks),
+            |(front_matter, blocks)| front_matter.map(Markdown::FrontMatter).into_iter().chain(blocks).collect()
+        )(input)
+    }
+
+    /// Parses blocks until the end of the input, or until a line that can't be parsed. Blank
+    /// lines between blocks are skipped.
+    pub fn parse_blocks(input: &str) -> IResult<&str, Vec<Markdown<'_>>> {
+        parse_nested_blocks(input, 0)
+    }
+
+    /// Parses blocks like [parse_blocks], inside of `depth` container blocks.
+    fn parse_nested_blocks(input: &str, depth: usize) -> IResult<&str, Vec<Markdown<'_>>> {
+        terminated(
+            many0(preceded(
+                opt(parse_blank_lines),
+                alt((
+                    map(parse_heading,
+                        |(level, text)| Markdown::Heading(level, text)),
+                    map(parse_thematic_break,
+                        |_| Markdown::ThematicBreak),
+                    map(|it| parse_nested_block_quote(it, depth),
+                        Markdown::BlockQuote),
+                    map(|it| parse_nested_unordered_list(it, depth),
+                        Markdown::UnorderedList),
+                    map(|it| parse_nested_ordered_list(it, depth),
+                        Markdown::OrderedList),
+                    map(parse_table,
+                        Markdown::Table),
+                    map(parse_code_block,
+                        Markdown::Codeblock),
+                    map(parse_indented_code_block,
+                        Markdown::Codeblock),
+                    map(|it| parse_nested_footnote_definition(it, depth),
+                        Markdown::FootnoteDefinition),
+                    map(parse_link_definition,
+                        Markdown::LinkDefinition),
+                    map(parse_setext_heading,
+                        |(level, text)| Markdown::Heading(level, text)),
+                    map(parse_paragraph,
+                        Markdown::Paragraph),
+                ))
+            )),
+            opt(parse_blank_lines)
+        )(input)
+    }
+
+    /// Matches YAML front matter between `---` lines (the closing one can also be `...`), or TOML
+    /// front matter between `+++` lines. It is not front matter if it is never closed.
+    pub fn parse_front_matter(input: &str) -> IResult<&str, FrontMatter<'_>> {
+        let (body_start, fence) = terminated(
+            alt((tag("---"), tag("+++"))),
+            pair(space0, parse_line_ending)
+        )(input)?;
+        let format = match fence {
+            "---" => FrontMatterFormat::Yaml,
+            _ => FrontMatterFormat::Toml,
+        };
+        let parse_closing_fence = |it| terminated(
+            verify(alt((tag("---"), tag("+++"), tag("..."))), |it: &str| {
+                it == fence || (it == "..." && format == FrontMatterFormat::Yaml)
+            }),
+            parse_blank_line
+        )(it);
+
+        let mut rest = body_start;
+        loop {
+            if let Ok((remaining, _)) = parse_closing_fence(rest) {
+                let raw = normalize_line_endings(&body_start[..body_start.len() - rest.len()]);
+                return Ok((remaining, FrontMatter {
+                    format,
+                    fields: parse_front_matter_fields(format, &raw),
+                    raw,
+                }));
+            }
+            if rest.is_empty() {
+                return fail(input);
+            }
+            (rest, _) = parse_rest_of_line(rest)?;
+        }
+    }
+
+    /// A paragraph is a line of text followed by every line up to a blank line or the start of
+    /// another block. The lines are joined with line breaks, and the whitespace around them is
+    /// removed.
+    pub fn parse_paragraph(input: &str) -> IResult<&str, MarkdownText<'_>> {
+        let (rest, lines) = parse_paragraph_lines(input)?;
+        let (_, text) = parse_paragraph_text_at(input, lines)?;
+        Ok((rest, text))
+    }
+
+    /// A setext heading is the lines of a paragraph, underlined with `=` for a level 1 heading or
+    /// `-` for a level 2 heading.
+    pub fn parse_setext_heading(input: &str) -> IResult<&str, (HeadingLevel, MarkdownText<'_>)> {
+        let (rest, (lines, level)) = pair(parse_paragraph_lines, parse_setext_underline)(input)?;
+        let (_, text) = parse_paragraph_text_at(input, lines)?;
+        Ok((rest, (level, text)))
+    }
+
+    /// Matches a line of `=` or `-` chars, and returns the level of the heading it underlines.
+    pub fn parse_setext_underline(input: &str) -> IResult<&str, HeadingLevel> {
+        delimited(
+            parse_indent,
             alt((
-                map(parse_heading,
-                    |(level, text)| Markdown::Heading(level, text)),
-                map(parse_unordered_list,
-                    Markdown::UnorderedList),
-                map(parse_ordered_list,
-                    Markdown::OrderedList),
-                map(parse_code_block,
-                    |(lang, body)| Markdown::Codeblock(lang, body)),
-                map(parse_markdown_text_until_eol,
-                    Markdown::Line),
+                map(take_while1(|it| it == '='), |_| HeadingLevel::Heading1),
+                map(take_while1(|it| it == '-'), |_| HeadingLevel::Heading2),
+            )),
+            parse_blank_line
+        )(input)
+    }
+
+    /// Matches the lines of a paragraph without parsing their text. They end at a blank line, the
+    /// start of another block, or a setext heading underline.
+    fn parse_paragraph_lines(input: &str) -> IResult<&str, &str> {
+        recognize(pair(
+            preceded(pair(not(parse_blank_line), not(parse_code_fence)), parse_rest_of_line),
+            many0(preceded(
+                tuple((
+                    not(parse_blank_line),
+                    not(parse_block_start),
+                    not(parse_table),
+                    not(parse_setext_underline)
+                )),
+                parse_rest_of_line
             ))
+        ))(input)
+    }
+
+    /// Parses `lines`, which start at `input`, with [parse_paragraph_text]. Errors point into
+    /// `lines`, so they are moved onto the same position in `input`.
+    fn parse_paragraph_text_at<'a>(input: &'a str, lines: &'a str) -> IResult<&'a str, MarkdownText<'a>> {
+        parse_paragraph_text(lines)
+            .map_err(|err| err.map_input(|stuck| &input[lines.len() - stuck.len()..]))
+    }
+
+    /// Parses the lines of a paragraph, which have already been split from the blocks around
+    /// them. Code spans can run over several of the lines.
+    fn parse_paragraph_text(lines: &str) -> IResult<&str, MarkdownText<'_>> {
+        let mut backtick_runs = BacktickRuns::default();
+        let (mut rest, mut tokens) = preceded(
+            space0,
+            terminated(|it| parse_inline_tokens(it, &mut backtick_runs), parse_eol)
+        )(lines)?;
+        while !rest.is_empty() {
+            let (remaining, line) = preceded(
+                space0,
+                terminated(|it| parse_inline_tokens(it, &mut backtick_runs), parse_eol)
+            )(rest)?;
+            let line_break = take_line_break(&mut tokens);
+            tokens.push(InlineToken::Inline(line_break));
+            tokens.extend(line);
+            rest = remaining;
+        }
+        trim_line_end(&mut tokens);
+        Ok((rest, resolve_emphasis(lines, tokens)))
+    }
+
+    /// Removes the whitespace at the end of the last line in `tokens`, and returns the break that
+    /// should follow it. A line that ends with two or more spaces or a `\` ends with a hard break.
+    fn take_line_break(tokens: &mut Vec<InlineToken<'_>>) -> MarkdownInline<'static> {
+        if let Some(InlineToken::Inline(MarkdownInline::Plaintext(last))) = tokens.last() {
+            if last.ends_with('\\') {
+                let len = last.len() - 1;
+                truncate_plaintext(tokens, len);
+                return MarkdownInline::HardBreak;
+            }
+        }
+        match trim_line_end(tokens) {
+            0 | 1 => MarkdownInline::SoftBreak,
+            _ => MarkdownInline::HardBreak,
+        }
+    }
+
+    /// Removes the whitespace at the end of `tokens`, and returns how many spaces were removed.
+    fn trim_line_end(tokens: &mut Vec<InlineToken<'_>>) -> usize {
+        let (len, spaces) = match tokens.last() {
+            Some(InlineToken::Inline(MarkdownInline::Plaintext(last))) => {
+                let trimmed = last.trim_end_matches([' ', '\t']);
+                (trimmed.len(), last[trimmed.len()..].matches(' ').count())
+            }
+            _ => return 0,
+        };
+        truncate_plaintext(tokens, len);
+        spaces
+    }
+
+    /// Shortens the plaintext at the end of `tokens` to `len` bytes, and removes it if it is left
+    /// empty.
+    fn truncate_plaintext(tokens: &mut Vec<InlineToken<'_>>, len: usize) {
+        if let Some(InlineToken::Inline(MarkdownInline::Plaintext(last))) = tokens.last_mut() {
+            match last {
+                Cow::Borrowed(it) => *it = &it[..len],
+                Cow::Owned(it) => it.truncate(len),
+            }
+            if last.is_empty() {
+                tokens.pop();
+            }
+        }
+    }
+
+    /// Matches a run of `*`, `_` or `~`, which may open or close emphasis or strikethrough.
+    pub fn parse_delimiter_run(input: &str) -> IResult<&str, &str> {
+        alt((
+            take_while1(|it| it == '*'),
+            take_while1(|it| it == '_'),
+            take_while1(|it| it == '~')
+        ))(input)
+    }
+
+    /// Matches a code span, which is opened by a run of backticks and closed by a run of exactly
+    /// as many backticks, and returns the code between them. The code can run over line endings,
+    /// but not over a blank line.
+    pub fn parse_inline_code(input: &str) -> IResult<&str, &str> {
+        let (rest, opening) = take_while1(|it| it == '`')(input)?;
+        let mut start = 0;
+        loop {
+            match find_backtick_run(rest, start) {
+                Ok((run_start, run_len)) if run_len == opening.len() => {
+                    return Ok((&rest[run_start + run_len..], &rest[..run_start]));
+                }
+                Ok((run_start, run_len)) => start = run_start + run_len,
+                Err(end) => {
+                    return Err(nom::Err::Error(nom::error::Error::new(
+                        &rest[end..],
+                        nom::error::ErrorKind::TakeUntil
+                    )));
+                }
+            }
+        }
+    }
+
+    /// Returns the offset and length of the first run of backticks in `input` after `start`. If
+    /// there is none before the first blank line, returns the offset of the line ending before
+    /// the blank line, or the length of `input` if there is no blank line either.
+    fn find_backtick_run(input: &str, mut start: usize) -> Result<(usize, usize), usize> {
+        while let Some(found) = input[start..].find(['`', '\n', '\r']) {
+            let index = start + found;
+            match parse_line_ending(&input[index..]) {
+                Ok((after, _)) if parse_blank_line(after).is_ok() => return Err(index),
+                Ok((after, _)) => start = input.len() - after.len(),
+                Err(_) => {
+                    let len = input[index..].bytes().take_while(|it| *it == b'`').count();
+                    return Ok((index, len));
+                }
+            }
+        }
+        Err(input.len())
+    }
+
+    /// The runs of backticks in a paragraph, which are looked for once a run that doesn't open a
+    /// code span is found. A later run can then be told to be plaintext without looking through
+    /// the rest of the paragraph again, as the reference implementations do.
+    #[derive(Default)]
+    struct BacktickRuns {
+        /// The length of the text from the last run of each length to the end.
+        last_runs: Option<HashMap<usize, usize>>,
+    }
+
+    impl BacktickRuns {
+        /// Parses a code span, see [parse_inline_code], or a run of backticks that doesn't open
+        /// one as plaintext.
+        fn parse<'a>(&mut self, input: &'a str) -> IResult<&'a str, MarkdownInline<'a>> {
+            let (after_run, run) = take_while1(|it| it == '`')(input)?;
+            let has_closer = match &self.last_runs {
+                Some(last_runs) => last_runs.get(&run.len()).is_some_and(|it| *it < input.len()),
+                None => true,
+            };
+            if has_closer {
+                match parse_inline_code(input) {
+                    Ok((rest, code)) => {
+                        return Ok((rest, MarkdownInline::InlineCode(normalize_code_span(code))));
+                    }
+                    Err(_) => self.find_runs(after_run),
+                }
+            }
+            Ok((after_run, MarkdownInline::Plaintext(run.into())))
+        }
+
+        fn find_runs(&mut self, input: &str) {
+            let last_runs = self.last_runs.get_or_insert_with(HashMap::new);
+            let mut start = 0;
+            while let Ok((run_start, run_len)) = find_backtick_run(input, start) {
+                last_runs.insert(run_len, input.len() - run_start);
+                start = run_start + run_len;
+            }
+        }
+    }
+
+    /// Turns the line endings in a code span into spaces, along with the indentation that follows
+    /// them, and strips one space from each end if the code both starts and ends with one.
+    pub fn normalize_code_span(code: &str) -> Cow<'_, str> {
+        let code: Cow<'_, str> = if code.contains(['\n', '\r']) {
+            let mut joined = String::new();
+            let mut rest = code;
+            while let Some(index) = rest.find(['\n', '\r']) {
+                let (after, _) = parse_line_ending(&rest[index..]).unwrap_or_default();
+                joined.push_str(&rest[..index]);
+                joined.push(' ');
+                rest = after.trim_start_matches([' ', '\t']);
+            }
+            joined.push_str(rest);
+            Cow::Owned(joined)
+        } else {
+            Cow::Borrowed(code)
+        };
+        let is_padded = code.len() >= 2 && code.starts_with(' ') && code.ends_with(' ');
+        if !is_padded || code.bytes().all(|it| it == b' ') {
+            return code;
+        }
+        match code {
+            Cow::Borrowed(it) => Cow::Borrowed(&it[1..it.len() - 1]),
+            Cow::Owned(it) => Cow::Owned(it[1..it.len() - 1].to_string()),
+        }
+    }
+
+    /// Returns the text, URL and title of a link.
+    pub fn parse_link(i: &str) -> IResult<&str, (&str, &str, Option<&str>)> {
+        map(
+            pair(parse_link_text, parse_link_target),
+            |(text, (url, title))| (text, url, title)
+        )(i)
+    }
+
+    /// Matches the text of a link or image in `[` and `]`, and returns the text. Brackets in the
+    /// text must either be balanced or escaped with a `\`, eg: `[a [b] c]` or `[a \] b]`, and
+    /// can't be nested too deeply, see [constants::MAX_NESTING_DEPTH].
+    pub fn parse_link_text(input: &str) -> IResult<&str, &str> {
+        let (text_start, _) = tag("[")(input)?;
+        let mut depth = 0;
+        let mut chars = text_start.char_indices();
+        while let Some((index, it)) = chars.next() {
+            match it {
+                '\\' => {
+                    chars.next();
+                }
+                '[' if depth + 1 == constants::MAX_NESTING_DEPTH => break,
+                '[' => depth += 1,
+                ']' if depth == 0 => return Ok((&text_start[index + 1..], &text_start[..index])),
+                ']' => depth -= 1,
+                _ => {}
+            }
+        }
+        fail(input)
+    }
+
+    /// Matches the `(url "title")` after the text of a link or image. The URL may be empty, and
+    /// the title is optional.
+    pub fn parse_link_target(input: &str) -> IResult<&str, (&str, Option<&str>)> {
+        delimited(
+            pair(tag("("), opt(parse_link_separator)),
+            pair(
+                map(opt(parse_link_destination), Option::unwrap_or_default),
+                opt(preceded(parse_link_separator, parse_link_title))
+            ),
+            pair(opt(parse_link_separator), tag(")"))
         )(input)
     }
 
-    pub fn parse_bold_italic(input: &str) -> IResult<&str, &str> {
+    /// Matches the whitespace between the parts of a link, which may include one line ending.
+    fn parse_link_separator(input: &str) -> IResult<&str, &str> {
         alt((
-            delimited(tag("***"), is_not("***"), tag("***")),
-            delimited(tag("___"), is_not("___"), tag("___")),
+            recognize(tuple((space0, parse_line_ending, space0))),
+            space1
         ))(input)
     }
 
-    pub fn parse_bold(input: &str) -> IResult<&str, &str> {
+    /// Matches a reference link, and returns its text, the label of the definition it refers to,
+    /// and the source after the text. These are `[text][label]`, `[label][]` or `[label]`.
+    pub fn parse_link_reference(input: &str) -> IResult<&str, (&str, &str, &str)> {
+        let (after_text, text) = parse_link_text(input)?;
+        let (rest, label) = alt((
+            delimited(tag("["), is_not("[]"), tag("]")),
+            map(tag("[]"), |_| text),
+            map(success(""), |_| text),
+        ))(after_text)?;
+        let closing = &input[1 + text.len()..input.len() - rest.len()];
+        Ok((rest, (text, label, closing)))
+    }
+
+    /// Matches a link reference definition, eg: `[ref]: /url "title"`. The URL and the title can
+    /// each be on the line after the one before them.
+    pub fn parse_link_definition(input: &str) -> IResult<&str, LinkDefinition<'_>> {
+        let (after_url, (label, url)) = pair(
+            preceded(
+                parse_indent,
+                delimited(tag("["), verify(is_not("[]"), |it: &str| !it.trim().is_empty()), tag("]:"))
+            ),
+            preceded(opt(parse_link_separator), parse_link_destination)
+        )(input)?;
+        let (rest, title) = alt((
+            map(terminated(preceded(parse_link_separator, parse_link_title), parse_blank_line), Some),
+            map(parse_blank_line, |_| None),
+        ))(after_url)?;
+        Ok((rest, LinkDefinition {
+            label: label.into(),
+            url: remove_backslash_escapes(url),
+            title: title.map(remove_backslash_escapes),
+        }))
+    }
+
+    /// Matches the URL of a link, which is either wrapped in `<` and `>` (and may then contain
+    /// spaces), or a run of chars that are not whitespace, where any parentheses are balanced.
+    pub fn parse_link_destination(input: &str) -> IResult<&str, &str> {
         alt((
-            delimited(tag("**"), is_not("**"), tag("**")),
-            delimited(tag("__"), is_not("__"), tag("__")),
+            delimited(tag("<"), take_until_unescaped(&['<', '>', '\n', '\r']), tag(">")),
+            parse_bare_link_destination
         ))(input)
     }
 
-    pub fn parse_italic(input: &str) -> IResult<&str, &str> {
+    fn parse_bare_link_destination(input: &str) -> IResult<&str, &str> {
+        let mut depth = 0;
+        let mut end = input.len();
+        let mut chars = input.char_indices().peekable();
+        while let Some((index, it)) = chars.next() {
+            match it {
+                '\\' if chars.peek().is_some_and(|(_, it)| it.is_ascii_punctuation()) => {
+                    chars.next();
+                }
+                '(' => depth += 1,
+                ')' if depth == 0 => {
+                    end = index;
+                    break;
+                }
+                ')' => depth -= 1,
+                it if it.is_whitespace() || it.is_control() => {
+                    end = index;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        if end == 0 || depth != 0 {
+            return fail(input);
+        }
+        Ok((&input[end..], &input[..end]))
+    }
+
+    /// Matches a link title in `"`, `'` or `(` and `)`, and returns the text inside them.
+    pub fn parse_link_title(input: &str) -> IResult<&str, &str> {
         alt((
-            delimited(tag("*"), is_not("*"), tag("*")),
-            delimited(tag("_"), is_not("_"), tag("_"))
+            delimited(tag("\""), take_until_unescaped(&['"']), tag("\"")),
+            delimited(tag("'"), take_until_unescaped(&['\'']), tag("'")),
+            delimited(tag("("), take_until_unescaped(&['(', ')']), tag(")")),
         ))(input)
     }
 
-    pub fn parse_inline_code(input: &str) -> IResult<&str, &str> {
-        delimited(tag("`"), is_not("`"), tag("`"))(input)
+    /// Returns a parser that matches text up to the first of `chars` that is not escaped with a
+    /// `\`, or up to the end of the input.
+    fn take_until_unescaped<'a>(chars: &'static [char]) -> impl Fn(&'a str) -> IResult<&'a str, &'a str> {
+        move |input: &'a str| {
+            let mut is_escaped = false;
+            for (index, it) in input.char_indices() {
+                if !is_escaped && chars.contains(&it) {
+                    return Ok((&input[index..], &input[..index]));
+                }
+                is_escaped = !is_escaped && it == '\\';
+            }
+            Ok(("", input))
+        }
     }
 
-    pub fn parse_link(i: &str) -> IResult<&str, (&str, &str)> {
-        pair(
-            delimited(tag("["), is_not("]"), tag("]")),
-            delimited(tag("("), is_not(")"), tag(")")),
-        )(i)
+    /// Removes the `\` of the backslash escapes in text that is not parsed as markdown, eg: the
+    /// URL and title of a link.
+    pub fn remove_backslash_escapes(text: &str) -> Cow<'_, str> {
+        if !text.contains('\\') {
+            return Cow::Borrowed(text);
+        }
+        let mut result = String::new();
+        let mut rest = text;
+        while let Some(index) = rest.find('\\') {
+            result.push_str(&rest[..index]);
+            match parse_backslash_escape(&rest[index..]) {
+                Ok((remaining, escaped)) => {
+                    result.push_str(escaped);
+                    rest = remaining;
+                }
+                Err(_) => {
+                    result.push('\\');
+                    rest = &rest[index + 1..];
+                }
+            }
+        }
+        result.push_str(rest);
+        Cow::Owned(result)
+    }
+
+    /// Matches a URL with a scheme or an email address in `<` and `>`, eg: `<https://example.com>`
+    /// or `<me@example.com>`. Email addresses link to `mailto:` URLs.
+    pub fn parse_autolink(input: &str) -> IResult<&str, Autolink<'_>> {
+        let parse_scheme = recognize(pair(
+            satisfy(|it| it.is_ascii_alphabetic()),
+            verify(
+                take_while1(|it: char| it.is_ascii_alphanumeric() || matches!(it, '+' | '.' | '-')),
+                |it: &str| it.len() <= 31
+            )
+        ));
+        let parse_uri = recognize(tuple((
+            parse_scheme,
+            tag(":"),
+            take_till(|it: char| it.is_ascii_control() || matches!(it, ' ' | '<' | '>'))
+        )));
+        let parse_email = recognize(tuple((
+            take_while1(|it: char| it.is_ascii_alphanumeric() || ".!#$%&'*+/=?^_`{|}~-".contains(it)),
+            tag("@"),
+            verify(
+                take_while1(|it: char| it.is_ascii_alphanumeric() || matches!(it, '-' | '.')),
+                |domain: &str| domain.split('.').all(|label| {
+                    !label.is_empty() && label.len() <= 63 && !label.starts_with('-') && !label.ends_with('-')
+                })
+            )
+        )));
+        delimited(
+            tag("<"),
+            alt((
+                map(parse_uri, |uri: &str| Autolink { text: uri.into(), url: uri.into() }),
+                map(parse_email, |email: &str| Autolink {
+                    text: email.into(),
+                    url: format!("mailto:{}", email).into(),
+                }),
+            )),
+            tag(">")
+        )(input)
     }
 
-    pub fn parse_image(i: &str) -> IResult<&str, (&str, &str)> {
-        pair(
-            delimited(tag("!["), is_not("]"), tag("]")),
-            delimited(tag("("), is_not(")"), tag(")")),
+    /// Returns the alt text, URL and title of an image.
+    pub fn parse_image(i: &str) -> IResult<&str, (&str, &str, Option<&str>)> {
+        map(
+            pair(preceded(tag("!"), parse_link_text), parse_link_target),
+            |(alt, (url, title))| (alt, url, title)
         )(i)
     }
 
@@ -85,346 +597,1229 @@ pub mod parser_impl {
     // characters then we return this slice.
     pub fn parse_plaintext(i: &str) -> IResult<&str, &str> {
         recognize(many1(preceded(
-            not(alt((tag("*"), tag("`"), tag("["), tag("!["), tag("\n")))),
+            not(alt((
+                tag("*"), tag("_"), tag("~"), tag("`"), tag("["), tag("!["), tag("\n"), tag("\r"),
+                parse_backslash_escape, recognize(parse_autolink)
+            ))),
             take(1u8),
         )))(i)
     }
 
-    /// Parse chunks of markdown text that are in a single line.
-    pub fn parse_markdown_inline(input: &str) -> IResult<&str, MarkdownInline> {
+    /// Matches a `\` followed by ASCII punctuation, and returns the punctuation, which is kept as
+    /// literal text.
+    pub fn parse_backslash_escape(input: &str) -> IResult<&str, &str> {
+        preceded(tag("\\"), recognize(satisfy(|it| it.is_ascii_punctuation())))(input)
+    }
+
+    /// Parse chunks of markdown text that are in a single line. Emphasis is not one of them, since
+    /// it can only be told apart from plaintext by looking at the rest of the text, see
+    /// [parse_markdown_text]. A run of backticks that doesn't open a code span, and a `[` or `![`
+    /// that doesn't open a link or image, are plaintext. Links can't contain other links, so the
+    /// `[` of one whose text does is plaintext too.
+    pub fn parse_markdown_inline(input: &str) -> IResult<&str, MarkdownInline<'_>> {
         alt((
-            map(parse_italic, MarkdownInline::Italic),
-            map(parse_bold, MarkdownInline::Bold),
-            map(parse_bold_italic, MarkdownInline::BoldItalic),
-            map(parse_inline_code, MarkdownInline::InlineCode),
-            map(parse_image, MarkdownInline::Image),
-            map(parse_link, MarkdownInline::Link),
-            map(parse_plaintext, MarkdownInline::Plaintext),
+            map(parse_inline_code, |it| MarkdownInline::InlineCode(normalize_code_span(it))),
+            map(take_while1(|it| it == '`'), |it: &str| MarkdownInline::Plaintext(it.into())),
+            map(parse_autolink, MarkdownInline::Autolink),
+            map(parse_image, |(alt, url, title)| MarkdownInline::Image(Image {
+                alt: alt.into(),
+                url: remove_backslash_escapes(url),
+                title: title.map(remove_backslash_escapes),
+            })),
+            map_opt(parse_link_tokens, |mut tokens| match (tokens.pop(), tokens.is_empty()) {
+                (Some(InlineToken::Inline(inline)), true) => Some(inline),
+                _ => None,
+            }),
+            map(alt((tag("!["), tag("["))), |it: &str| MarkdownInline::Plaintext(it.into())),
+            map(parse_plaintext, |it| MarkdownInline::Plaintext(it.into())),
         ))(input)
     }
 
-    pub fn parse_markdown_text_until_eol(input: &str) -> IResult<&str, MarkdownText> {
-        terminated(
-            many0(parse_markdown_inline),
-            tag("\n")
-        )(input)
+    /// Parses a link, a footnote reference or a reference link, see [parse_link],
+    /// [parse_footnote_label] and [parse_link_reference]. Links can't contain other links, so if
+    /// the text of one does, its `[` is plaintext, followed by the tokens of the text. This way
+    /// the text is only parsed once, however deeply links are nested in it.
+    fn parse_link_tokens(input: &str) -> IResult<&str, Vec<InlineToken<'_>>> {
+        let (_, text) = parse_link_text(input)?;
+        let text_tokens = parse_nested_tokens(text);
+        if contains_link(&text_tokens) {
+            let mut tokens = vec![InlineToken::Inline(MarkdownInline::Plaintext(input[..1].into()))];
+            tokens.extend(text_tokens);
+            return Ok((&input[1 + text.len()..], tokens));
+        }
+        let (rest, inline) = if let Ok((rest, (_, url, title))) = parse_link(input) {
+            (rest, MarkdownInline::Link(Link {
+                text: resolve_emphasis(text, text_tokens),
+                url: remove_backslash_escapes(url),
+                title: title.map(remove_backslash_escapes),
+            }))
+        } else if let Ok((rest, label)) = parse_footnote_label(input) {
+            (rest, MarkdownInline::FootnoteReference(FootnoteReference {
+                label: label.into(),
+                number: None,
+                index: 0,
+            }))
+        } else {
+            let (rest, (_, label, closing)) = parse_link_reference(input)?;
+            let text = resolve_emphasis(text, text_tokens);
+            (rest, MarkdownInline::LinkReference((text, label.into(), closing.into())))
+        };
+        Ok((rest, vec![InlineToken::Inline(inline)]))
+    }
+
+    /// Splits the text inside a link into tokens, eg: the `see *docs*` in `[see *docs*](url)`.
+    /// Text that can't be parsed is kept as plaintext.
+    fn parse_nested_tokens(input: &str) -> Vec<InlineToken<'_>> {
+        match all_consuming(|it| parse_inline_tokens(it, &mut BacktickRuns::default()))(input) {
+            Ok((_, tokens)) => tokens,
+            Err(_) => vec![InlineToken::Inline(MarkdownInline::Plaintext(input.into()))],
+        }
+    }
+
+    /// Whether `tokens` contain a link, an autolink or a footnote reference, which is rendered as
+    /// a link.
+    fn contains_link(tokens: &[InlineToken<'_>]) -> bool {
+        tokens.iter().any(|it| matches!(
+            it,
+            InlineToken::Inline(
+                MarkdownInline::Link(_)
+                | MarkdownInline::Autolink(_)
+                | MarkdownInline::FootnoteReference(_)
+            )
+        ))
+    }
+
+    /// Parses text up to the end of the line, and resolves which `*` and `_` are emphasis, and
+    /// which `~~` are strikethrough.
+    pub fn parse_markdown_text(input: &str) -> IResult<&str, MarkdownText<'_>> {
+        let (rest, tokens) = parse_inline_tokens(input, &mut BacktickRuns::default())?;
+        Ok((rest, resolve_emphasis(input, tokens)))
+    }
+
+    /// Splits text up to the end of the line into inlines, delimiter runs and escaped chars.
+    /// `backtick_runs` is shared by the lines of a paragraph.
+    fn parse_inline_tokens<'a>(
+        input: &'a str,
+        backtick_runs: &mut BacktickRuns,
+    ) -> IResult<&'a str, Vec<InlineToken<'a>>> {
+        let mut tokens = vec![];
+        let mut rest = input;
+        loop {
+            if let Ok((remaining, escaped)) = parse_backslash_escape(rest) {
+                tokens.push(InlineToken::Escaped(escaped));
+                rest = remaining;
+            } else if let Ok((remaining, run)) = parse_delimiter_run(rest) {
+                let before = input[..input.len() - rest.len()].chars().next_back();
+                let after = remaining.chars().next();
+                tokens.push(InlineToken::Delimiter(DelimiterRun::new(run, before, after)));
+                rest = remaining;
+            } else if let Ok((remaining, inline)) = backtick_runs.parse(rest) {
+                tokens.push(InlineToken::Inline(inline));
+                rest = remaining;
+            } else if let Ok((remaining, link_tokens)) = parse_link_tokens(rest) {
+                tokens.extend(link_tokens);
+                rest = remaining;
+            } else if let Ok((remaining, inline)) = parse_markdown_inline(rest) {
+                tokens.push(InlineToken::Inline(inline));
+                rest = remaining;
+            } else {
+                return Ok((rest, tokens));
+            }
+        }
+    }
+
+    pub fn parse_markdown_text_until_eol(input: &str) -> IResult<&str, MarkdownText<'_>> {
+        let (rest, line) = parse_rest_of_line(input)?;
+        let (_, text) = all_consuming(parse_markdown_text)(line)
+            .map_err(|err| err.map_input(|stuck| &input[line.len() - stuck.len()..]))?;
+        Ok((rest, text))
+    }
+
+    /// Matches the end of a line, which is either a line ending or the end of the input.
+    pub fn parse_eol(input: &str) -> IResult<&str, &str> {
+        alt((parse_line_ending, eof))(input)
+    }
+
+    /// Matches `\r\n`, `\n` or a lone `\r`.
+    pub fn parse_line_ending(input: &str) -> IResult<&str, &str> {
+        alt((tag("\r\n"), tag("\n"), tag("\r")))(input)
     }
 
-    /// Matches one or more `#` chars.
+    /// Matches up to 3 spaces of indentation and 1 to 6 `#` chars, followed by a space, a tab or
+    /// the end of the line. A line that starts with 7 or more `#` chars is not a heading.
     pub fn parse_heading_tag(input: &str) -> IResult<&str, HeadingLevel> {
-        map(
-            terminated(
+        map_res(
+            delimited(
+                parse_indent,
                 take_while1(|it| it == constants::HEADING_CHAR),
-                tag(constants::SPACE_STR)
+                alt((tag(constants::SPACE_STR), tag("\t"), peek(parse_eol)))
             ),
-            |it: &str| HeadingLevel::from(it.len()),
+            |it: &str| HeadingLevel::try_from(it.len()),
         )(input)
     }
 
-    /// This combines a tuple of the heading tag and the rest of the line.
-    pub fn parse_heading(input: &str) -> IResult<&str, (HeadingLevel, MarkdownText)> {
-        tuple(
-            (parse_heading_tag, parse_markdown_text_until_eol)
-        )(input)
+    /// Matches a line of three or more `-`, `*` or `_` chars, which may have spaces or tabs
+    /// between them, eg: `---` or `* * *`.
+    pub fn parse_thematic_break(input: &str) -> IResult<&str, &str> {
+        recognize(preceded(
+            parse_indent,
+            verify(parse_rest_of_line, |line: &str| {
+                let mut chars = line.chars().filter(|it| *it != ' ' && *it != '\t');
+                let first = chars.next().unwrap_or_default();
+                "-*_".contains(first) && chars.clone().count() >= 2 && chars.all(|it| it == first)
+            })
+        ))(input)
+    }
+
+    /// This combines a tuple of the heading tag and the rest of the line, without the whitespace
+    /// around it or a closing sequence of `#` chars.
+    pub fn parse_heading(input: &str) -> IResult<&str, (HeadingLevel, MarkdownText<'_>)> {
+        let (after_tag, level) = parse_heading_tag(input)?;
+        let (rest, line) = parse_rest_of_line(after_tag)?;
+        let content = remove_closing_sequence(line);
+        // Errors point into `content`, so they are moved onto the same position in `input`.
+        let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+        let content_end = input.len() - after_tag.len() + indent + content.len();
+        let (_, text) = all_consuming(parse_markdown_text)(content)
+            .map_err(|err| err.map_input(|stuck| &input[content_end - stuck.len()..]))?;
+        Ok((rest, (level, text)))
     }
 
+    /// Trims the text of an ATX heading and removes its closing sequence, which is a run of `#`
+    /// chars at the end of the text that is preceded by a space or a tab, eg: `## heading ##`.
+    fn remove_closing_sequence(text: &str) -> &str {
+        let text = text.trim_matches([' ', '\t']);
+        let without_closing_sequence = text.trim_end_matches(constants::HEADING_CHAR);
+        if without_closing_sequence.is_empty() {
+            ""
+        } else if without_closing_sequence.ends_with([' ', '\t']) {
+            without_closing_sequence.trim_end_matches([' ', '\t'])
+        } else {
+            text
+        }
+    }
+
+    /// Matches up to 3 spaces of indentation and a `-`, `*` or `+` bullet followed by a space,
+    /// and returns the bullet. A bullet at the end of the line starts an empty item.
     pub fn parse_unordered_list_tag(i: &str) -> IResult<&str, &str> {
-        terminated(tag("-"), tag(" "))(i)
+        delimited(parse_indent, recognize(one_of("-*+")), parse_list_tag_end)(i)
+    }
+
+    pub fn parse_unordered_list_element(i: &str) -> IResult<&str, ListItem<'_>> {
+        parse_list_item(i, parse_unordered_list_tag, 0)
     }
 
-    pub fn parse_unordered_list_element(i: &str) -> IResult<&str, MarkdownText> {
-        preceded(parse_unordered_list_tag, parse_markdown_text_until_eol)(i)
+    /// Every item of the list has the same bullet, a different bullet starts a new list.
+    pub fn parse_unordered_list(i: &str) -> IResult<&str, Vec<ListItem<'_>>> {
+        parse_nested_unordered_list(i, 0)
     }
 
-    pub fn parse_unordered_list(i: &str) -> IResult<&str, Vec<MarkdownText>> {
-        many1(parse_unordered_list_element)(i)
+    fn parse_nested_unordered_list(i: &str, depth: usize) -> IResult<&str, Vec<ListItem<'_>>> {
+        let (_, bullet) = parse_unordered_list_tag(i)?;
+        let parse_tag = move |it| delimited(parse_indent, tag(bullet), parse_list_tag_end)(it);
+        parse_list_items(i, move |it| parse_list_item(it, parse_tag, depth), parse_tag)
     }
 
+    /// Matches up to 3 spaces of indentation and a number followed by a `.` and a space, and
+    /// returns the number. A number at the end of the line starts an empty item.
     pub fn parse_ordered_list_tag(i: &str) -> IResult<&str, &str> {
-        terminated(
+        delimited(
+            parse_indent,
             terminated(take_while1(|d| is_digit(d as u8)), tag(".")),
-            tag(" "),
+            parse_list_tag_end,
         )(i)
     }
 
-    pub fn parse_ordered_list_element(i: &str) -> IResult<&str, MarkdownText> {
-        preceded(parse_ordered_list_tag, parse_markdown_text_until_eol)(i)
+    /// Matches the space after a list tag, or the end of the line after a tag with no text.
+    fn parse_list_tag_end(input: &str) -> IResult<&str, &str> {
+        alt((tag(" "), peek(parse_eol)))(input)
+    }
+
+    pub fn parse_ordered_list_element(i: &str) -> IResult<&str, ListItem<'_>> {
+        parse_list_item(i, parse_ordered_list_tag, 0)
+    }
+
+    pub fn parse_ordered_list(i: &str) -> IResult<&str, Vec<ListItem<'_>>> {
+        parse_nested_ordered_list(i, 0)
+    }
+
+    fn parse_nested_ordered_list(i: &str, depth: usize) -> IResult<&str, Vec<ListItem<'_>>> {
+        let parse_item = move |it| parse_list_item(it, parse_ordered_list_tag, depth);
+        parse_list_items(i, parse_item, parse_ordered_list_tag)
+    }
+
+    /// Matches one or more list items. Items may be separated by blank lines.
+    fn parse_list_items<'a>(
+        input: &'a str,
+        parse_item: impl Fn(&'a str) -> IResult<&'a str, ListItem<'a>> + Copy,
+        parse_tag: impl Fn(&'a str) -> IResult<&'a str, &'a str> + Copy,
+    ) -> IResult<&'a str, Vec<ListItem<'a>>> {
+        map(
+            pair(
+                parse_item,
+                many0(preceded(opt(pair(parse_blank_lines, peek(parse_tag))), parse_item))
+            ),
+            |(first, mut rest)| {
+                rest.insert(0, first);
+                rest
+            }
+        )(input)
+    }
+
+    /// A list item is made of the rest of the line after its tag, followed by every line that is
+    /// indented at least as far as the text after the tag (with the indentation removed), or that
+    /// is a lazy continuation line. The tag itself may be indented by up to 3 spaces, which counts
+    /// towards how far the lines after it have to be indented. These lines are parsed as blocks,
+    /// which is how lists nest. The first line may start with a task list marker. A line like
+    /// `- - -` is a thematic break rather than an item. The item is inside of `depth` container
+    /// blocks.
+    fn parse_list_item<'a>(
+        input: &'a str,
+        parse_tag: impl Fn(&'a str) -> IResult<&'a str, &'a str>,
+        depth: usize,
+    ) -> IResult<&'a str, ListItem<'a>> {
+        if depth >= constants::MAX_NESTING_DEPTH {
+            return fail(input);
+        }
+        let (after_tag, _) = preceded(not(parse_thematic_break), parse_tag)(input)?;
+        let tag_len = input.len() - after_tag.len();
+        // The text of an item that starts with a blank line is indented one space past its tag.
+        let (after_spaces, indent) = match parse_blank_line(after_tag) {
+            Ok(_) => (after_tag, tag_len + usize::from(!input[..tag_len].ends_with(' '))),
+            Err(_) => {
+                let (after_spaces, _) = take_while_m_n(0, 3, |it| it == ' ')(after_tag)?;
+                (after_spaces, input.len() - after_spaces.len())
+            }
+        };
+        let indent = " ".repeat(indent);
+        let (after_marker, checked) = opt(parse_task_list_marker)(after_spaces)?;
+        let (rest, lines) = parse_item_lines(after_marker, &indent)?;
+        match parse_contained_blocks(&lines, depth + 1) {
+            Some(blocks) => Ok((rest, ListItem { checked, blocks })),
+            None => fail(input),
+        }
+    }
+
+    /// Collects the rest of the first line of a list item or footnote, and the lines after it
+    /// that are indented by `indent` (which is removed) or are lazy continuation lines. Blank
+    /// lines only belong to it if an indented line follows them.
+    fn parse_item_lines<'a>(input: &'a str, indent: &str) -> IResult<&'a str, Vec<Cow<'a, str>>> {
+        let parse_indented_line = |it| preceded(
+            not(parse_blank_line),
+            preceded(tag(indent), parse_rest_of_line)
+        )(it);
+
+        let (mut rest, first_line) = parse_rest_of_line(input)?;
+        let mut lines = vec![Cow::Borrowed(first_line)];
+        let mut lazy_continuation = LazyContinuation::default();
+        lazy_continuation.push(first_line);
+        loop {
+            if let Ok((remaining, line)) = parse_indented_line(rest) {
+                lines.push(Cow::Borrowed(line));
+                lazy_continuation.push(line);
+                rest = remaining;
+            } else if let Ok((remaining, blank_lines)) =
+                terminated(parse_blank_lines, peek(parse_indented_line))(rest)
+            {
+                lines.extend(blank_lines.iter().map(|_| Cow::Borrowed("")));
+                lazy_continuation.push("");
+                rest = remaining;
+            } else if let (true, Ok((remaining, line))) = (
+                lazy_continuation.is_allowed(),
+                parse_lazy_line(rest)
+            ) {
+                lazy_continuation.push(&line);
+                lines.push(line);
+                rest = remaining;
+            } else {
+                return Ok((rest, lines));
+            }
+        }
+    }
+
+    /// Matches the `[^label]` of a footnote reference or definition, and returns the label.
+    pub fn parse_footnote_label(input: &str) -> IResult<&str, &str> {
+        delimited(tag("[^"), is_not(" \t\r\n[]"), tag("]"))(input)
+    }
+
+    /// Matches a footnote definition, eg: `[^1]: text`. Like a list item, it holds the blocks
+    /// parsed from its lines, and the lines after the first are indented by four spaces.
+    pub fn parse_footnote_definition(input: &str) -> IResult<&str, FootnoteDefinition<'_>> {
+        parse_nested_footnote_definition(input, 0)
+    }
+
+    fn parse_nested_footnote_definition(
+        input: &str,
+        depth: usize,
+    ) -> IResult<&str, FootnoteDefinition<'_>> {
+        if depth >= constants::MAX_NESTING_DEPTH {
+            return fail(input);
+        }
+        let (after_label, label) = delimited(
+            parse_indent,
+            parse_footnote_label,
+            pair(tag(":"), space0)
+        )(input)?;
+        let (rest, lines) = parse_item_lines(after_label, "    ")?;
+        match parse_contained_blocks(&lines, depth + 1) {
+            Some(blocks) => Ok((rest, FootnoteDefinition { label: label.into(), blocks })),
+            None => fail(input),
+        }
+    }
+
+    /// Matches `[ ] ` or `[x] ` (or `[X] `) and returns whether the box is checked.
+    pub fn parse_task_list_marker(input: &str) -> IResult<&str, bool> {
+        terminated(
+            delimited(
+                tag("["),
+                alt((map(tag(" "), |_| false), map(one_of("xX"), |_| true))),
+                tag("]")
+            ),
+            take_while1(|it| it == ' ' || it == '\t')
+        )(input)
+    }
+
+    /// A codeblock is an opening fence, the lines of its body, and a closing fence. The body
+    /// ends at the first closing fence, so a block can contain shorter fences or fences made of
+    /// the other char. A codeblock that is never closed runs to the end of the input. The
+    /// indentation of the opening fence is removed from each line.
+    pub fn parse_code_block(input: &str) -> IResult<&str, CodeBlock<'_>> {
+        let (body_start, (indent, fence, info)) = parse_code_fence(input)?;
+        let fence_char = fence.chars().next().unwrap_or_default();
+        let parse_closing_fence = |it| tuple((
+            parse_indent,
+            verify(take_while1(|it| it == fence_char), |it: &str| it.len() >= fence.len()),
+            parse_blank_line
+        ))(it);
+
+        let mut rest = body_start;
+        let body = loop {
+            if let Ok((remaining, _)) = parse_closing_fence(rest) {
+                let body = &body_start[..body_start.len() - rest.len()];
+                rest = remaining;
+                break body;
+            }
+            if rest.is_empty() {
+                break body_start;
+            }
+            (rest, _) = parse_rest_of_line(rest)?;
+        };
+
+        let (lang, attributes) = match info.split_once([' ', '\t']) {
+            Some((lang, attributes)) => (lang, attributes.trim()),
+            None => (info, ""),
+        };
+        let mut body = remove_indent(body, indent.len());
+        // The last line of a codeblock that runs to the end of the input has no line ending.
+        if !body.is_empty() && !body.ends_with('\n') {
+            body.to_mut().push('\n');
+        }
+        Ok((rest, CodeBlock {
+            lang: Some(lang).filter(|it| !it.is_empty()).map(Cow::Borrowed),
+            attributes: Some(attributes).filter(|it| !it.is_empty()).map(Cow::Borrowed),
+            body,
+        }))
+    }
+
+    /// Matches the opening fence of a codeblock, which is three or more backticks or tildes
+    /// followed by an info string. Returns the indentation, the fence and the trimmed info
+    /// string. The info string after backticks can't contain a backtick, so that a line like
+    /// ```` ```code``` ```` is inline code instead.
+    pub fn parse_code_fence(input: &str) -> IResult<&str, (&str, &str, &str)> {
+        let (rest, (indent, fence)) = pair(
+            parse_indent,
+            alt((
+                verify(take_while1(|it| it == '`'), |it: &str| it.len() >= 3),
+                verify(take_while1(|it| it == '~'), |it: &str| it.len() >= 3),
+            ))
+        )(input)?;
+        let (rest, info) = verify(
+            parse_rest_of_line,
+            |it: &str| fence.starts_with('~') || !it.contains('`')
+        )(rest)?;
+        Ok((rest, (indent, fence, info.trim())))
+    }
+
+    /// An indented codeblock is made of lines that are indented by four spaces or a tab, with the
+    /// indentation removed. Blank lines between them are part of the block. It can't interrupt
+    /// a paragraph, since those lines are read as a continuation of the paragraph instead.
+    pub fn parse_indented_code_block(input: &str) -> IResult<&str, CodeBlock<'_>> {
+        let parse_code_line = |it| preceded(
+            pair(not(parse_blank_line), parse_code_indent),
+            parse_rest_of_line
+        )(it);
+
+        let (mut rest, first_line) = parse_code_line(input)?;
+        let mut body = format!("{first_line}\n");
+        loop {
+            if let Ok((remaining, line)) = parse_code_line(rest) {
+                body.push_str(line);
+                body.push('\n');
+                rest = remaining;
+            } else if let Ok((remaining, blank_lines)) =
+                terminated(parse_blank_lines, peek(parse_code_line))(rest)
+            {
+                for line in blank_lines {
+                    if let Ok((whitespace, _)) = parse_code_indent(line) {
+                        body.push_str(whitespace.trim_end_matches(['\n', '\r']));
+                    }
+                    body.push('\n');
+                }
+                rest = remaining;
+            } else {
+                break;
+            }
+        }
+        Ok((rest, CodeBlock { lang: None, attributes: None, body: Cow::Owned(body) }))
+    }
+
+    /// Matches the indentation of an indented codeblock, which is four spaces or a tab (that may
+    /// follow up to 3 spaces).
+    pub fn parse_code_indent(input: &str) -> IResult<&str, &str> {
+        alt((tag("    "), recognize(pair(parse_indent, tag("\t")))))(input)
+    }
+
+    /// Removes up to `indent` spaces from the start of each line of `body`, and normalizes its
+    /// line endings.
+    fn remove_indent(body: &str, indent: usize) -> Cow<'_, str> {
+        if indent == 0 {
+            return normalize_line_endings(body);
+        }
+        let mut rest = body;
+        let mut unindented = String::new();
+        while !rest.is_empty() {
+            let (remaining, (_, line, line_ending)) = tuple((
+                take_while_m_n(0, indent, |it| it == ' '),
+                take_till(|it| it == '\n' || it == '\r'),
+                alt((parse_line_ending, eof))
+            ))(rest).unwrap_or_default();
+            unindented.push_str(line);
+            unindented.push_str(if line_ending.is_empty() { "" } else { "\n" });
+            rest = remaining;
+        }
+        Cow::Owned(unindented)
+    }
+
+    /// A table is a header row, a delimiter row with as many cells, and the rows that follow up
+    /// to a blank line or the start of another block. Rows with too few cells are padded with
+    /// empty cells, and extra cells are dropped.
+    pub fn parse_table(input: &str) -> IResult<&str, Table<'_>> {
+        let (rest, header) = verify(parse_rest_of_line, |it: &str| has_unescaped_pipe(it))(input)?;
+        let (mut rest, alignments) = parse_table_delimiter_row(rest)?;
+        if split_table_row(header).len() != alignments.len() {
+            return fail(input);
+        }
+        let header = parse_table_row(header, alignments.len());
+        let mut rows = vec![];
+        while let Ok((remaining, row)) = preceded(
+            not(alt((parse_blank_line, parse_block_start))),
+            parse_rest_of_line
+        )(rest) {
+            rows.push(parse_table_row(row, alignments.len()));
+            rest = remaining;
+        }
+
+        match (header, rows.into_iter().collect::<Option<Vec<_>>>()) {
+            (Some(header), Some(rows)) => Ok((rest, Table { header, alignments, rows })),
+            _ => fail(input),
+        }
     }
 
-    pub fn parse_ordered_list(i: &str) -> IResult<&str, Vec<MarkdownText>> {
-        many1(parse_ordered_list_element)(i)
+    /// Matches a row like `| :--- | :---: | ---: |`. The outer pipes are optional, but a table
+    /// with a single column needs at least one of them.
+    pub fn parse_table_delimiter_row(input: &str) -> IResult<&str, Vec<Alignment>> {
+        verify(
+            delimited(
+                pair(parse_indent, opt(tag("|"))),
+                separated_list1(tag("|"), parse_table_delimiter_cell),
+                pair(opt(tag("|")), parse_blank_line)
+            ),
+            |_: &Vec<Alignment>| has_unescaped_pipe(input.split(['\n', '\r']).next().unwrap_or_default())
+        )(input)
     }
 
-    pub fn parse_code_block(input: &str) -> IResult<&str, (/* lang */ &str, /* body */ &str)> {
-        tuple(
-            (parse_code_block_lang, parse_code_block_body)
+    pub fn parse_table_delimiter_cell(input: &str) -> IResult<&str, Alignment> {
+        map(
+            delimited(
+                space0,
+                tuple((opt(tag(":")), take_while1(|it| it == '-'), opt(tag(":")))),
+                space0
+            ),
+            |(left, _, right)| match (left, right) {
+                (Some(_), Some(_)) => Alignment::Center,
+                (Some(_), None) => Alignment::Left,
+                (None, Some(_)) => Alignment::Right,
+                (None, None) => Alignment::None,
+            }
         )(input)
     }
 
-    pub fn parse_code_block_body(input: &str) -> IResult<&str, &str> {
-        delimited(tag("\n"), is_not("```"), tag("```"))(input)
+    /// Parses the cells of a table row as text, with exactly `columns` cells.
+    fn parse_table_row(row: &str, columns: usize) -> Option<Vec<MarkdownText<'_>>> {
+        let mut cells = split_table_row(row)
+            .into_iter()
+            .take(columns)
+            .map(|cell| match cell {
+                Cow::Borrowed(cell) => all_consuming(parse_markdown_text)(cell)
+                    .ok()
+                    .map(|(_, text)| text),
+                Cow::Owned(cell) => all_consuming(parse_markdown_text)(&cell)
+                    .ok()
+                    .map(|(_, text)| text.into_iter().map(MarkdownInline::into_owned).collect()),
+            })
+            .collect::<Option<Vec<_>>>()?;
+        cells.resize(columns, vec![]);
+        Some(cells)
+    }
+
+    /// Splits a table row on the pipes that are not escaped with a `\`, and trims the cells.
+    /// The pipes at the start and end of the row are optional. Cells that contain an escaped pipe
+    /// have the `\` removed.
+    fn split_table_row(row: &str) -> Vec<Cow<'_, str>> {
+        let row = row.trim();
+        let row = row.strip_prefix('|').unwrap_or(row);
+        let row = match row.strip_suffix('|') {
+            Some(it) if !it.ends_with('\\') => it,
+            _ => row,
+        };
+        let mut cells = vec![];
+        let mut cell_start = 0;
+        let mut escaped = false;
+        for (index, it) in row.char_indices() {
+            match it {
+                '|' if !escaped => {
+                    cells.push(&row[cell_start..index]);
+                    cell_start = index + 1;
+                }
+                _ => escaped = it == '\\' && !escaped,
+            }
+        }
+        cells.push(&row[cell_start..]);
+        cells
+            .into_iter()
+            .map(|cell| {
+                let cell = cell.trim();
+                if cell.contains("\\|") {
+                    Cow::Owned(cell.replace("\\|", "|"))
+                } else {
+                    Cow::Borrowed(cell)
+                }
+            })
+            .collect()
+    }
+
+    fn has_unescaped_pipe(line: &str) -> bool {
+        line.char_indices()
+            .any(|(index, it)| it == '|' && !line[..index].ends_with('\\'))
+    }
+
+    /// Matches up to 3 spaces of indentation.
+    pub fn parse_indent(input: &str) -> IResult<&str, &str> {
+        take_while_m_n(0, 3, |it| it == ' ')(input)
     }
 
-    pub fn parse_code_block_lang(input: &str) -> IResult<&str, &str> {
+    /// Returns the rest of the line and consumes its line ending.
+    pub fn parse_rest_of_line(input: &str) -> IResult<&str, &str> {
+        terminated(take_till(|it| it == '\n' || it == '\r'), parse_eol)(input)
+    }
+
+    /// Matches a line that only contains whitespace, or the end of the input.
+    pub fn parse_blank_line(input: &str) -> IResult<&str, &str> {
+        recognize(pair(take_while(|it| it == ' ' || it == '\t'), parse_eol))(input)
+    }
+
+    /// Matches one or more blank lines, not including the end of the input.
+    pub fn parse_blank_lines(input: &str) -> IResult<&str, Vec<&str>> {
+        many1(preceded(not(eof), parse_blank_line))(input)
+    }
+
+    /// Matches the start of a line that begins a block other than a [Markdown::Paragraph], eg: a list
+    /// item or a heading. These lines can't be lazy continuation lines. An empty list item is not
+    /// one of them, since it can't interrupt a paragraph.
+    pub fn parse_block_start(input: &str) -> IResult<&str, &str> {
         alt((
-            preceded(tag("```"), parse_plaintext),
-            map(tag("```"), |_| "__UNKNOWN_LANGUAGE__"),
+            recognize(parse_heading_tag),
+            parse_thematic_break,
+            terminated(parse_unordered_list_tag, not(parse_blank_line)),
+            terminated(parse_ordered_list_tag, not(parse_blank_line)),
+            recognize(parse_code_fence),
+            recognize(pair(parse_indent, tag(">"))),
+            recognize(tuple((parse_indent, parse_footnote_label, tag(":")))),
         ))(input)
     }
 
+    /// Matches a `>` marker, followed by an optional space, and returns the rest of the line.
+    pub fn parse_block_quote_line(input: &str) -> IResult<&str, &str> {
+        preceded(
+            tuple((parse_indent, tag(">"), opt(tag(" ")))),
+            parse_rest_of_line
+        )(input)
+    }
+
+    /// Matches a line that continues the text of the previous line without repeating the `>`
+    /// marker(s) of its blockquote.
+    pub fn parse_lazy_continuation_line(input: &str) -> IResult<&str, &str> {
+        preceded(
+            not(alt((
+                parse_blank_line,
+                parse_block_start,
+                parse_unordered_list_tag,
+                parse_ordered_list_tag
+            ))),
+            parse_rest_of_line
+        )(input)
+    }
+
+    /// Matches a lazy continuation line like [parse_lazy_continuation_line]. Such a line can only
+    /// continue a paragraph, so one that looks like a setext heading underline, eg: `===`, has
+    /// its first char escaped to keep it as text once the lines of the container are parsed.
+    fn parse_lazy_line(input: &str) -> IResult<&str, Cow<'_, str>> {
+        map(parse_lazy_continuation_line, |line| match parse_setext_underline(line) {
+            Ok(_) => Cow::Owned(format!("\\{}", line.trim_start())),
+            Err(_) => Cow::Borrowed(line),
+        })(input)
+    }
+
+    /// Collects the lines of a blockquote with their `>` marker removed, and parses them as
+    /// blocks. Nested blockquotes are parsed when those lines are, since they still start with a
+    /// `>`.
+    pub fn parse_block_quote(input: &str) -> IResult<&str, Vec<Markdown<'_>>> {
+        parse_nested_block_quote(input, 0)
+    }
+
+    /// Parses a blockquote like [parse_block_quote], inside of `depth` container blocks. It isn't
+    /// one if it would be nested too deeply, see [constants::MAX_NESTING_DEPTH].
+    fn parse_nested_block_quote(input: &str, depth: usize) -> IResult<&str, Vec<Markdown<'_>>> {
+        if depth >= constants::MAX_NESTING_DEPTH {
+            return fail(input);
+        }
+        let (mut rest, first_line) = parse_block_quote_line(input)?;
+        let mut lines = vec![Cow::Borrowed(first_line)];
+        let mut lazy_continuation = LazyContinuation::default();
+        lazy_continuation.push(first_line);
+        loop {
+            let next_line = if lazy_continuation.is_allowed() {
+                alt((map(parse_block_quote_line, Cow::Borrowed), parse_lazy_line))(rest)
+            } else {
+                map(parse_block_quote_line, Cow::Borrowed)(rest)
+            };
+            match next_line {
+                Ok((remaining, line)) => {
+                    lazy_continuation.push(&line);
+                    lines.push(line);
+                    rest = remaining;
+                }
+                Err(_) => break,
+            }
+        }
+
+        match parse_contained_blocks(&lines, depth + 1) {
+            Some(blocks) => Ok((rest, blocks)),
+            None => fail(input),
+        }
+    }
+
+    /// Parses the lines of a container block, eg: a blockquote, as blocks. A single line can be
+    /// parsed in place, but several lines have to be joined into a new string first. The blocks
+    /// are inside of `depth` container blocks.
+    fn parse_contained_blocks<'a>(lines: &[Cow<'a, str>], depth: usize) -> Option<Vec<Markdown<'a>>> {
+        match lines {
+            [Cow::Borrowed(line)] => parse_all_contained_blocks(line, depth),
+            _ => parse_all_contained_blocks(&lines.join("\n"), depth)
+                .map(|blocks| blocks.into_iter().map(Markdown::into_owned).collect()),
+        }
+    }
+
+    /// Parses all of `input` as blocks. A codeblock that is not closed by the end of the
+    /// container ends with it.
+    fn parse_all_contained_blocks(input: &str, depth: usize) -> Option<Vec<Markdown<'_>>> {
+        all_consuming(|it| parse_nested_blocks(it, depth))(input).ok().map(|(_, blocks)| blocks)
+    }
+
+    /// Lazy continuation lines are only allowed after a line of text. Since they are found before
+    /// the contents of a container block are parsed, this keeps track of whether the last line
+    /// (with the markers of any nested blockquotes removed) is text, and whether the lines so far
+    /// leave a codeblock open, as each line is collected.
+    #[derive(Default)]
+    struct LazyContinuation {
+        is_inside_codeblock: bool,
+        is_after_text: bool,
+    }
+
+    impl LazyContinuation {
+        fn push(&mut self, line: &str) {
+            let line = remove_block_quote_markers(line);
+            if parse_code_fence(line).is_ok() {
+                self.is_inside_codeblock = !self.is_inside_codeblock;
+            }
+            self.is_after_text = parse_blank_line(line).is_err() && parse_block_start(line).is_err();
+        }
+
+        fn is_allowed(&self) -> bool {
+            !self.is_inside_codeblock && self.is_after_text
+        }
+    }
+
+    /// Removes the `>` markers of any nested blockquotes from the start of `line`.
+    fn remove_block_quote_markers(line: &str) -> &str {
+        let mut line = line;
+        while let Ok((rest, _)) = tuple((parse_indent, tag(">"), opt(tag(" "))))(line) {
+            line = rest;
+        }
+        line
+    }
+
 }
 pub use parser_impl::*;
 
+/// Replaces `\r\n` and lone `\r` line endings with `\n`, only allocating if there are any.
+pub fn normalize_line_endings(text: &str) -> Cow<'_, str> {
+    if text.contains('\r') {
+        Cow::Owned(text.replace("\r\n", "\n").replace('\r', "\n"))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use nom::{error::Error, error::ErrorKind, Err as NomErr};
+    use std::collections::HashMap;
 
     #[test]
-    fn test_parse_italic() {
-        assert_eq!(parse_italic("*here is italic*"), Ok(("", "here is italic")));
-
-        assert_eq!(parse_italic("_here is italic_"), Ok(("", "here is italic")));
-
+    fn test_parse_inline_code() {
         assert_eq!(
-            parse_italic("*here is italic"),
-            Err(NomErr::Error(Error {
-                input: "*here is italic",
-                code: ErrorKind::Tag
-            }))
+            parse_inline_code("`here is code`\n"),
+            Ok(("\n", "here is code"))
         );
-
+        assert_eq!(parse_inline_code("`` a`b ``c"), Ok(("c", " a`b ")));
+        assert_eq!(parse_inline_code("```a``b````c```"), Ok(("", "a``b````c")));
+        assert_eq!(parse_inline_code("`a\nb`"), Ok(("", "a\nb")));
         assert_eq!(
-            parse_italic("here is italic*"),
+            parse_inline_code("`a\n\nb`"),
             Err(NomErr::Error(Error {
-                input: "here is italic*",
-                code: ErrorKind::Tag,
+                input: "\n\nb`",
+                code: ErrorKind::TakeUntil
             }))
         );
-
         assert_eq!(
-            parse_italic("here is italic"),
+            parse_inline_code("`here is code"),
             Err(NomErr::Error(Error {
-                input: "here is italic",
-                code: ErrorKind::Tag
+                input: "",
+                code: ErrorKind::TakeUntil
             }))
         );
-
         assert_eq!(
-            parse_italic("*"),
+            parse_inline_code("here is code`"),
             Err(NomErr::Error(Error {
-                input: "*",
-                code: ErrorKind::Tag
+                input: "here is code`",
+                code: ErrorKind::TakeWhile1
             }))
         );
-
         assert_eq!(
-            parse_italic("**"),
+            parse_inline_code("``a`"),
             Err(NomErr::Error(Error {
-                input: "**",
-                code: ErrorKind::Tag
+                input: "",
+                code: ErrorKind::TakeUntil
             }))
         );
-
         assert_eq!(
-            parse_italic(""),
+            parse_inline_code("``"),
             Err(NomErr::Error(Error {
                 input: "",
-                code: ErrorKind::Tag
+                code: ErrorKind::TakeUntil
             }))
         );
-
         assert_eq!(
-            parse_italic("**we are doing bold**"),
+            parse_inline_code(""),
             Err(NomErr::Error(Error {
-                input: "**we are doing bold**",
-                code: ErrorKind::Tag
+                input: "",
+                code: ErrorKind::TakeWhile1
             }))
         );
     }
 
     #[test]
-    fn test_parse_bold_italic() {
+    fn test_parse_backtick_runs() {
+        assert_eq!(
+            parse_markdown_text("`` a ` b ` c"),
+            Ok((
+                "",
+                vec![
+                    MarkdownInline::Plaintext("`` a ".into()),
+                    MarkdownInline::InlineCode("b".into()),
+                    MarkdownInline::Plaintext(" c".into()),
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_markdown_text("` a `` b ``` c ``"),
+            Ok((
+                "",
+                vec![
+                    MarkdownInline::Plaintext("` a ".into()),
+                    MarkdownInline::InlineCode("b ``` c".into()),
+                ]
+            ))
+        );
         assert_eq!(
-            parse_bold_italic("***here is bitalic***"),
-            Ok(("", "here is bitalic"))
+            parse_paragraph("`` a\n`b`\n``"),
+            Ok(("", vec![MarkdownInline::InlineCode("a `b`".into())]))
         );
+    }
+
+    #[test]
+    fn test_normalize_code_span() {
+        assert_eq!(normalize_code_span("code"), "code");
+        assert_eq!(normalize_code_span(" `` "), "``");
+        assert_eq!(normalize_code_span("  a  "), " a ");
+        assert_eq!(normalize_code_span(" a"), " a");
+        assert_eq!(normalize_code_span("  "), "  ");
+        assert_eq!(normalize_code_span("a\nb\r\n  c\rd  "), "a b c d  ");
+        assert_eq!(normalize_code_span("\na\n"), "a");
+    }
 
+    #[test]
+    fn test_parse_link() {
         assert_eq!(
-            parse_bold("***here is bitalic"),
+            parse_link("[title](https://www.example.com)"),
+            Ok(("", ("title", "https://www.example.com", None)))
+        );
+        assert_eq!(
+            parse_link("[Rust](https://en.wikipedia.org/wiki/Rust_(language)))"),
+            Ok((
+                ")",
+                (
+                    "Rust",
+                    "https://en.wikipedia.org/wiki/Rust_(language)",
+                    None
+                )
+            ))
+        );
+        assert_eq!(
+            parse_link("[a]( /url \"Title \\\"quoted\\\"\" )"),
+            Ok(("", ("a", "/url", Some("Title \\\"quoted\\\""))))
+        );
+        assert_eq!(
+            parse_link("[a](<my url> 'Title')"),
+            Ok(("", ("a", "my url", Some("Title"))))
+        );
+        assert_eq!(parse_link("[a]()"), Ok(("", ("a", "", None))));
+        assert_eq!(parse_link("[\\]](u)"), Ok(("", ("\\]", "u", None))));
+        assert_eq!(parse_link("[a [b] c](u)"), Ok(("", ("a [b] c", "u", None))));
+        assert_eq!(
+            parse_link("[a [b](u)"),
             Err(NomErr::Error(Error {
-                input: "***here is bitalic",
-                code: ErrorKind::Tag
+                input: "[a [b](u)",
+                code: ErrorKind::Fail
             }))
         );
-
         assert_eq!(
-            parse_bold("here is bitalic***"),
+            parse_link("[a](/url(x"),
             Err(NomErr::Error(Error {
-                input: "here is bitalic***",
+                input: "/url(x",
                 code: ErrorKind::Tag
             }))
         );
-
         assert_eq!(
-            parse_bold_italic("___here is bitalic___"),
-            Ok(("", "here is bitalic"))
-        );
-
-        assert_eq!(
-            parse_bold_italic("___here is bitalic"),
+            parse_inline_code(""),
             Err(NomErr::Error(Error {
                 input: "",
-                code: ErrorKind::Tag
+                code: ErrorKind::TakeWhile1
             }))
         );
+    }
 
+    #[test]
+    fn test_parse_link_reference() {
         assert_eq!(
-            parse_bold_italic("here is bitalic___"),
+            parse_link_reference("[text][ref] after"),
+            Ok((" after", ("text", "ref", "][ref]")))
+        );
+        assert_eq!(
+            parse_link_reference("[ref][]"),
+            Ok(("", ("ref", "ref", "][]")))
+        );
+        assert_eq!(
+            parse_link_reference("[ref] [x]"),
+            Ok((" [x]", ("ref", "ref", "]")))
+        );
+        assert_eq!(
+            parse_link_reference("[ref"),
             Err(NomErr::Error(Error {
-                input: "here is bitalic___",
-                code: ErrorKind::Tag
+                input: "[ref",
+                code: ErrorKind::Fail
             }))
         );
     }
 
     #[test]
-    fn test_parse_bold() {
-        assert_eq!(parse_bold("**here is bold**"), Ok(("", "here is bold")));
-
-        assert_eq!(parse_bold("__here is bold__"), Ok(("", "here is bold")));
-
+    fn test_parse_footnote_label() {
+        assert_eq!(parse_footnote_label("[^1] a"), Ok((" a", "1")));
+        assert_eq!(parse_footnote_label("[^my-note]:"), Ok((":", "my-note")));
         assert_eq!(
-            parse_bold("**here is bold"),
+            parse_footnote_label("[^a b]"),
             Err(NomErr::Error(Error {
-                input: "**here is bold",
+                input: " b]",
                 code: ErrorKind::Tag
             }))
         );
-
         assert_eq!(
-            parse_bold("here is bold**"),
+            parse_footnote_label("[1]"),
             Err(NomErr::Error(Error {
-                input: "here is bold**",
+                input: "[1]",
                 code: ErrorKind::Tag
             }))
         );
+    }
 
+    #[test]
+    fn test_parse_footnote_definition() {
         assert_eq!(
-            parse_bold("here is bold"),
-            Err(NomErr::Error(Error {
-                input: "here is bold",
-                code: ErrorKind::Tag
-            }))
+            parse_footnote_definition("[^1]: One\ntwo\n\n    Three\n\nFour"),
+            Ok((
+                "\nFour",
+                FootnoteDefinition {
+                    label: "1".into(),
+                    blocks: vec![
+                        Markdown::Paragraph(vec![
+                            MarkdownInline::Plaintext("One".into()),
+                            MarkdownInline::SoftBreak,
+                            MarkdownInline::Plaintext("two".into()),
+                        ]),
+                        Markdown::Paragraph(vec![MarkdownInline::Plaintext("Three".into())]),
+                    ]
+                }
+            ))
         );
-
         assert_eq!(
-            parse_bold("****"),
-            Err(NomErr::Error(Error {
-                input: "****",
-                code: ErrorKind::Tag
-            }))
+            parse_footnote_definition("[^a]: One\n[^b]: Two\n"),
+            Ok((
+                "[^b]: Two\n",
+                FootnoteDefinition {
+                    label: "a".into(),
+                    blocks: vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                        "One".into()
+                    )])]
+                }
+            ))
         );
-
         assert_eq!(
-            parse_bold("**"),
-            Err(NomErr::Error(Error {
-                input: "**",
-                code: ErrorKind::Tag
-            }))
+            parse_markdown("See[^1].\n"),
+            Ok((
+                "",
+                vec![Markdown::Paragraph(vec![
+                    MarkdownInline::Plaintext("See".into()),
+                    MarkdownInline::FootnoteReference(FootnoteReference {
+                        label: "1".into(),
+                        number: None,
+                        index: 0
+                    }),
+                    MarkdownInline::Plaintext(".".into()),
+                ])]
+            ))
         );
+    }
 
+    #[test]
+    fn test_parse_link_definition() {
         assert_eq!(
-            parse_bold("*"),
-            Err(NomErr::Error(Error {
-                input: "*",
-                code: ErrorKind::Tag
-            }))
+            parse_link_definition("[Ref]: /url \"title\"\nrest"),
+            Ok((
+                "rest",
+                LinkDefinition {
+                    label: "Ref".into(),
+                    url: "/url".into(),
+                    title: Some("title".into())
+                }
+            ))
+        );
+        assert_eq!(
+            parse_link_definition("   [ref]:\n  <a b>\n  'title'  \n"),
+            Ok((
+                "",
+                LinkDefinition {
+                    label: "ref".into(),
+                    url: "a b".into(),
+                    title: Some("title".into())
+                }
+            ))
+        );
+        assert_eq!(
+            parse_link_definition("[ref]: /url\n\"not a title\" text"),
+            Ok((
+                "\"not a title\" text",
+                LinkDefinition {
+                    label: "ref".into(),
+                    url: "/url".into(),
+                    title: None
+                }
+            ))
         );
-
         assert_eq!(
-            parse_bold(""),
+            parse_link_definition("[ref]: /url \"title\" text"),
             Err(NomErr::Error(Error {
-                input: "",
-                code: ErrorKind::Tag
+                input: "\"title\" text",
+                code: ErrorKind::Eof
             }))
         );
-
         assert_eq!(
-            parse_bold("*this is italic*"),
+            parse_link_definition("[ ]: /url"),
             Err(NomErr::Error(Error {
-                input: "*this is italic*",
-                code: ErrorKind::Tag
+                input: " ]: /url",
+                code: ErrorKind::Verify
             }))
         );
+        assert_eq!(
+            parse_markdown("text\n[ref]: /url\n\n[ref]: /url\n"),
+            Ok((
+                "",
+                vec![
+                    Markdown::Paragraph(vec![
+                        MarkdownInline::Plaintext("text".into()),
+                        MarkdownInline::SoftBreak,
+                        MarkdownInline::LinkReference((
+                            vec![MarkdownInline::Plaintext("ref".into())],
+                            "ref".into(),
+                            "]".into()
+                        )),
+                        MarkdownInline::Plaintext(": /url".into()),
+                    ]),
+                    Markdown::LinkDefinition(LinkDefinition {
+                        label: "ref".into(),
+                        url: "/url".into(),
+                        title: None
+                    }),
+                ]
+            ))
+        );
     }
 
     #[test]
-    fn test_parse_inline_code() {
-        assert_eq!(parse_bold("**here is bold**\n"), Ok(("\n", "here is bold")));
+    fn test_parse_strikethrough() {
         assert_eq!(
-            parse_inline_code("`here is code"),
-            Err(NomErr::Error(Error {
-                input: "",
-                code: ErrorKind::Tag
-            }))
+            parse_markdown_text("~~a *b*~~ c"),
+            Ok((
+                "",
+                vec![
+                    MarkdownInline::Strikethrough(vec![
+                        MarkdownInline::Plaintext("a ".into()),
+                        MarkdownInline::Italic(vec![MarkdownInline::Plaintext("b".into())]),
+                    ]),
+                    MarkdownInline::Plaintext(" c".into()),
+                ]
+            ))
         );
         assert_eq!(
-            parse_inline_code("here is code`"),
-            Err(NomErr::Error(Error {
-                input: "here is code`",
-                code: ErrorKind::Tag
-            }))
+            parse_markdown_text("~~~a~~~ ~~ a~~ ~a~"),
+            Ok((
+                "",
+                vec![MarkdownInline::Plaintext("~~~a~~~ ~~ a~~ ~a~".into())]
+            ))
         );
         assert_eq!(
-            parse_inline_code("``"),
-            Err(NomErr::Error(Error {
-                input: "`",
-                code: ErrorKind::IsNot
-            }))
+            parse_markdown_text("**~~a *b*~~** ~~c"),
+            Ok((
+                "",
+                vec![
+                    MarkdownInline::Bold(vec![MarkdownInline::Strikethrough(vec![
+                        MarkdownInline::Plaintext("a ".into()),
+                        MarkdownInline::Italic(vec![MarkdownInline::Plaintext("b".into())]),
+                    ])]),
+                    MarkdownInline::Plaintext(" ~~c".into()),
+                ]
+            ))
         );
         assert_eq!(
-            parse_inline_code("`"),
-            Err(NomErr::Error(Error {
-                input: "",
-                code: ErrorKind::IsNot
-            }))
+            parse_markdown_text("*a ~~b* c~~"),
+            Ok((
+                "",
+                vec![
+                    MarkdownInline::Italic(vec![MarkdownInline::Plaintext("a ~~b".into())]),
+                    MarkdownInline::Plaintext(" c~~".into()),
+                ]
+            ))
         );
         assert_eq!(
-            parse_inline_code(""),
-            Err(NomErr::Error(Error {
-                input: "",
-                code: ErrorKind::Tag
-            }))
+            parse_paragraph("~~a\nb~~"),
+            Ok((
+                "",
+                vec![MarkdownInline::Strikethrough(vec![
+                    MarkdownInline::Plaintext("a".into()),
+                    MarkdownInline::SoftBreak,
+                    MarkdownInline::Plaintext("b".into()),
+                ])]
+            ))
         );
     }
 
     #[test]
-    fn test_parse_link() {
+    fn test_parse_autolink() {
         assert_eq!(
-            parse_link("[title](https://www.example.com)"),
-            Ok(("", ("title", "https://www.example.com")))
+            parse_autolink("<https://example.com/a*b*> after"),
+            Ok((
+                " after",
+                Autolink {
+                    text: "https://example.com/a*b*".into(),
+                    url: "https://example.com/a*b*".into()
+                }
+            ))
         );
         assert_eq!(
-            parse_inline_code(""),
+            parse_autolink("<me+you@example.com>"),
+            Ok((
+                "",
+                Autolink {
+                    text: "me+you@example.com".into(),
+                    url: "mailto:me+you@example.com".into()
+                }
+            ))
+        );
+        assert_eq!(
+            parse_autolink("<a b>"),
             Err(NomErr::Error(Error {
-                input: "",
+                input: " b>",
                 code: ErrorKind::Tag
             }))
         );
+        assert_eq!(
+            parse_autolink("<me@-example.com>"),
+            Err(NomErr::Error(Error {
+                input: "-example.com>",
+                code: ErrorKind::Verify
+            }))
+        );
+        assert_eq!(
+            parse_markdown_text("1 < 2 <http://a.b>"),
+            Ok((
+                "",
+                vec![
+                    MarkdownInline::Plaintext("1 < 2 ".into()),
+                    MarkdownInline::Autolink(Autolink {
+                        text: "http://a.b".into(),
+                        url: "http://a.b".into()
+                    })
+                ]
+            ))
+        );
     }
 
     #[test]
     fn test_parse_image() {
         assert_eq!(
             parse_image("![alt text](image.jpg)"),
-            Ok(("", ("alt text", "image.jpg")))
+            Ok(("", ("alt text", "image.jpg", None)))
+        );
+        assert_eq!(
+            parse_image("![alt text](image.jpg (A title))"),
+            Ok(("", ("alt text", "image.jpg", Some("A title"))))
         );
         assert_eq!(
             parse_inline_code(""),
             Err(NomErr::Error(Error {
                 input: "",
-                code: ErrorKind::Tag
+                code: ErrorKind::TakeWhile1
             }))
         );
     }
@@ -514,6 +1909,8 @@ mod tests {
                 code: ErrorKind::Not
             }))
         );
+        assert_eq!(parse_plaintext("a\\*b"), Ok(("\\*b", "a")));
+        assert_eq!(parse_plaintext("a\\b\\"), Ok(("", "a\\b\\")));
         assert_eq!(
             parse_plaintext(""),
             Err(NomErr::Error(Error {
@@ -527,43 +1924,57 @@ mod tests {
     fn test_parse_markdown_inline() {
         assert_eq!(
             parse_markdown_inline("*here is italic*"),
-            Ok(("", MarkdownInline::Italic("here is italic")))
+            Err(NomErr::Error(Error {
+                input: "*here is italic*",
+                code: ErrorKind::Not
+            }))
         );
         assert_eq!(
-            parse_markdown_inline("**here is bold**"),
-            Ok(("", MarkdownInline::Bold("here is bold")))
+            parse_markdown_inline("`here is code`"),
+            Ok(("", MarkdownInline::InlineCode("here is code".into())))
         );
         assert_eq!(
-            parse_markdown_inline("`here is code`"),
-            Ok(("", MarkdownInline::InlineCode("here is code")))
+            parse_markdown_inline("``not code`"),
+            Ok(("not code`", MarkdownInline::Plaintext("``".into())))
         );
         assert_eq!(
             parse_markdown_inline("[title](https://www.example.com)"),
             Ok((
                 "",
-                (MarkdownInline::Link(("title", "https://www.example.com")))
+                (MarkdownInline::Link(Link {
+                    text: vec![MarkdownInline::Plaintext("title".into())],
+                    url: "https://www.example.com".into(),
+                    title: None
+                }))
             ))
         );
         assert_eq!(
             parse_markdown_inline("![alt text](image.jpg)"),
-            Ok(("", (MarkdownInline::Image(("alt text", "image.jpg")))))
+            Ok((
+                "",
+                (MarkdownInline::Image(Image {
+                    alt: "alt text".into(),
+                    url: "image.jpg".into(),
+                    title: None
+                }))
+            ))
         );
         assert_eq!(
             parse_markdown_inline("here is plaintext!"),
-            Ok(("", MarkdownInline::Plaintext("here is plaintext!")))
+            Ok(("", MarkdownInline::Plaintext("here is plaintext!".into())))
         );
         assert_eq!(
             parse_markdown_inline("here is some plaintext *but what if we italicize?"),
             Ok((
                 "*but what if we italicize?",
-                MarkdownInline::Plaintext("here is some plaintext ")
+                MarkdownInline::Plaintext("here is some plaintext ".into())
             ))
         );
         assert_eq!(
             parse_markdown_inline("here is some plaintext \n*but what if we italicize?"),
             Ok((
                 "\n*but what if we italicize?",
-                MarkdownInline::Plaintext("here is some plaintext ")
+                MarkdownInline::Plaintext("here is some plaintext ".into())
             ))
         );
         assert_eq!(
@@ -589,7 +2000,7 @@ mod tests {
             parse_markdown_text_until_eol("here is some plaintext\n"),
             Ok((
                 "",
-                vec![MarkdownInline::Plaintext("here is some plaintext")]
+                vec![MarkdownInline::Plaintext("here is some plaintext".into())]
             ))
         );
         assert_eq!(
@@ -597,20 +2008,22 @@ mod tests {
             Ok((
                 "",
                 vec![
-                    MarkdownInline::Plaintext("here is some plaintext "),
-                    MarkdownInline::Italic("but what if we italicize?"),
+                    MarkdownInline::Plaintext("here is some plaintext ".into()),
+                    MarkdownInline::Italic(vec![MarkdownInline::Plaintext(
+                        "but what if we italicize?".into()
+                    )]),
                 ]
             ))
         );
         assert_eq!(
             parse_markdown_text_until_eol("here is some plaintext *but what if we italicize?* I guess it doesn't **matter** in my `code`\n"),
             Ok(("", vec![
-                MarkdownInline::Plaintext("here is some plaintext "),
-                MarkdownInline::Italic("but what if we italicize?"),
-                MarkdownInline::Plaintext(" I guess it doesn't "),
-                MarkdownInline::Bold("matter"),
-                MarkdownInline::Plaintext(" in my "),
-                MarkdownInline::InlineCode("code"),
+                MarkdownInline::Plaintext("here is some plaintext ".into()),
+                MarkdownInline::Italic(vec![MarkdownInline::Plaintext("but what if we italicize?".into())]),
+                MarkdownInline::Plaintext(" I guess it doesn't ".into()),
+                MarkdownInline::Bold(vec![MarkdownInline::Plaintext("matter".into())]),
+                MarkdownInline::Plaintext(" in my ".into()),
+                MarkdownInline::InlineCode("code".into()),
             ]))
         );
         assert_eq!(
@@ -618,38 +2031,86 @@ mod tests {
             Ok((
                 "",
                 vec![
-                    MarkdownInline::Plaintext("here is some plaintext "),
-                    MarkdownInline::Italic("but what if we italicize?"),
+                    MarkdownInline::Plaintext("here is some plaintext ".into()),
+                    MarkdownInline::Italic(vec![MarkdownInline::Plaintext(
+                        "but what if we italicize?".into()
+                    )]),
                 ]
             ))
         );
         assert_eq!(
             parse_markdown_text_until_eol("here is some plaintext *but what if we italicize?"),
-            Err(NomErr::Error(Error {
-                input: "*but what if we italicize?",
-                code: ErrorKind::Tag
-            })) // Ok(("*but what if we italicize?", vec![MarkdownInline::Plaintext(String::from("here is some plaintext "))]))
+            Ok((
+                "",
+                vec![MarkdownInline::Plaintext(
+                    "here is some plaintext *but what if we italicize?".into()
+                )]
+            ))
+        );
+        assert_eq!(
+            parse_markdown_text_until_eol("here is some plaintext `but what if we code?"),
+            Ok((
+                "",
+                vec![MarkdownInline::Plaintext(
+                    "here is some plaintext `but what if we code?".into()
+                )]
+            ))
+        );
+        assert_eq!(
+            parse_markdown_text_until_eol("here is some plaintext *but what if we italicize?*"),
+            Ok((
+                "",
+                vec![
+                    MarkdownInline::Plaintext("here is some plaintext ".into()),
+                    MarkdownInline::Italic(vec![MarkdownInline::Plaintext(
+                        "but what if we italicize?".into()
+                    )]),
+                ]
+            ))
         );
+        assert_eq!(parse_markdown_text_until_eol(""), Ok(("", vec![])));
     }
 
     #[test]
     fn test_parse_header_tag() {
-        assert_eq!(parse_heading_tag("# "), Ok(("", 1.into())));
-        assert_eq!(parse_heading_tag("### "), Ok(("", 3.into())));
-        assert_eq!(parse_heading_tag("# h1"), Ok(("h1", 1.into())));
-        assert_eq!(parse_heading_tag("# h1"), Ok(("h1", 1.into())));
+        assert_eq!(parse_heading_tag("# "), Ok(("", HeadingLevel::Heading1)));
+        assert_eq!(parse_heading_tag("### "), Ok(("", HeadingLevel::Heading3)));
+        assert_eq!(
+            parse_heading_tag("# h1"),
+            Ok(("h1", HeadingLevel::Heading1))
+        );
+        assert_eq!(
+            parse_heading_tag("#\th1"),
+            Ok(("h1", HeadingLevel::Heading1))
+        );
+        assert_eq!(
+            parse_heading_tag("   ## h2"),
+            Ok(("h2", HeadingLevel::Heading2))
+        );
+        assert_eq!(parse_heading_tag("#"), Ok(("", HeadingLevel::Heading1)));
+        assert_eq!(
+            parse_heading_tag("###\n"),
+            Ok(("\n", HeadingLevel::Heading3))
+        );
         assert_eq!(
             parse_heading_tag(" "),
             Err(NomErr::Error(Error {
-                input: " ",
+                input: "",
+                code: ErrorKind::TakeWhile1
+            }))
+        );
+        assert_eq!(
+            parse_heading_tag("    # code"),
+            Err(NomErr::Error(Error {
+                input: " # code",
                 code: ErrorKind::TakeWhile1
             }))
         );
         assert_eq!(
-            parse_heading_tag("#"),
+            parse_heading_tag("####### h7"),
             Err(NomErr::Error(Error {
-                input: "",
-                code: ErrorKind::Tag
+                input: "####### h7",
+                code: ErrorKind::MapRes
             }))
         );
     }
@@ -658,87 +2119,279 @@ mod tests {
     fn test_parse_header() {
         assert_eq!(
             parse_heading("# h1\n"),
-            Ok(("", (1.into(), vec![MarkdownInline::Plaintext("h1")])))
+            Ok((
+                "",
+                (
+                    HeadingLevel::Heading1,
+                    vec![MarkdownInline::Plaintext("h1".into())]
+                )
+            ))
         );
         assert_eq!(
             parse_heading("## h2\n"),
-            Ok(("", (2.into(), vec![MarkdownInline::Plaintext("h2")])))
+            Ok((
+                "",
+                (
+                    HeadingLevel::Heading2,
+                    vec![MarkdownInline::Plaintext("h2".into())]
+                )
+            ))
         );
         assert_eq!(
-            parse_heading("###  h3\n"),
-            Ok(("", (3.into(), vec![MarkdownInline::Plaintext(" h3")])))
+            parse_heading("###  h3  \n"),
+            Ok((
+                "",
+                (
+                    HeadingLevel::Heading3,
+                    vec![MarkdownInline::Plaintext("h3".into())]
+                )
+            ))
         );
         assert_eq!(
             parse_heading("###h3"),
             Err(NomErr::Error(Error {
                 input: "h3",
-                code: ErrorKind::Tag
+                code: ErrorKind::Eof
             }))
         );
         assert_eq!(
             parse_heading("###"),
-            Err(NomErr::Error(Error {
-                input: "",
-                code: ErrorKind::Tag
-            }))
+            Ok(("", (HeadingLevel::Heading3, vec![])))
         );
         assert_eq!(
-            parse_heading(""),
-            Err(NomErr::Error(Error {
-                input: "",
-                code: ErrorKind::TakeWhile1
-            }))
+            parse_heading("### "),
+            Ok(("", (HeadingLevel::Heading3, vec![])))
+        );
+        assert_eq!(
+            parse_heading("# \n"),
+            Ok(("", (HeadingLevel::Heading1, vec![])))
         );
         assert_eq!(
-            parse_heading("#"),
+            parse_heading(""),
             Err(NomErr::Error(Error {
                 input: "",
-                code: ErrorKind::Tag
+                code: ErrorKind::TakeWhile1
             }))
         );
-        assert_eq!(parse_heading("# \n"), Ok(("", (1.into(), vec![]))));
         assert_eq!(
             parse_heading("# test"),
-            Err(NomErr::Error(Error {
-                input: "",
-                code: ErrorKind::Tag
-            }))
+            Ok((
+                "",
+                (
+                    HeadingLevel::Heading1,
+                    vec![MarkdownInline::Plaintext("test".into())]
+                )
+            ))
         );
     }
 
     #[test]
-    fn test_parse_unordered_list_tag() {
-        assert_eq!(parse_unordered_list_tag("- "), Ok(("", "-")));
+    fn test_parse_header_closing_sequence() {
         assert_eq!(
-            parse_unordered_list_tag("- and some more"),
-            Ok(("and some more", "-"))
+            parse_heading("## h2 ##\n"),
+            Ok((
+                "",
+                (
+                    HeadingLevel::Heading2,
+                    vec![MarkdownInline::Plaintext("h2".into())]
+                )
+            ))
         );
         assert_eq!(
-            parse_unordered_list_tag("-"),
-            Err(NomErr::Error(Error {
-                input: "",
-                code: ErrorKind::Tag
-            }))
+            parse_heading("# h1 #########   "),
+            Ok((
+                "",
+                (
+                    HeadingLevel::Heading1,
+                    vec![MarkdownInline::Plaintext("h1".into())]
+                )
+            ))
         );
         assert_eq!(
-            parse_unordered_list_tag("-and some more"),
-            Err(NomErr::Error(Error {
-                input: "and some more",
-                code: ErrorKind::Tag
-            }))
+            parse_heading("# C#"),
+            Ok((
+                "",
+                (
+                    HeadingLevel::Heading1,
+                    vec![MarkdownInline::Plaintext("C#".into())]
+                )
+            ))
         );
         assert_eq!(
-            parse_unordered_list_tag("--"),
-            Err(NomErr::Error(Error {
-                input: "-",
-                code: ErrorKind::Tag
-            }))
+            parse_heading("# h1 \\#"),
+            Ok((
+                "",
+                (
+                    HeadingLevel::Heading1,
+                    vec![MarkdownInline::Plaintext("h1 #".into())]
+                )
+            ))
+        );
+        assert_eq!(
+            parse_heading("### ###"),
+            Ok(("", (HeadingLevel::Heading3, vec![])))
+        );
+        assert_eq!(
+            parse_markdown("####### h7\n"),
+            Ok((
+                "",
+                vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "####### h7".into()
+                )])]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_thematic_break() {
+        assert_eq!(parse_thematic_break("---\nrest"), Ok(("rest", "---\n")));
+        assert_eq!(parse_thematic_break(" * * *  "), Ok(("", " * * *  ")));
+        assert_eq!(parse_thematic_break("_____"), Ok(("", "_____")));
+        assert_eq!(
+            parse_thematic_break("--"),
+            Err(NomErr::Error(Error {
+                input: "--",
+                code: ErrorKind::Verify
+            }))
+        );
+        assert_eq!(
+            parse_thematic_break("-*-"),
+            Err(NomErr::Error(Error {
+                input: "-*-",
+                code: ErrorKind::Verify
+            }))
+        );
+        assert_eq!(
+            parse_thematic_break("--- a"),
+            Err(NomErr::Error(Error {
+                input: "--- a",
+                code: ErrorKind::Verify
+            }))
+        );
+        assert_eq!(
+            parse_markdown("text\n***\n- ***\n"),
+            Ok((
+                "",
+                vec![
+                    Markdown::Paragraph(vec![MarkdownInline::Plaintext("text".into())]),
+                    Markdown::ThematicBreak,
+                    Markdown::UnorderedList(vec![vec![Markdown::ThematicBreak].into()]),
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_setext_heading() {
+        assert_eq!(
+            parse_setext_heading("Title\n=====\nrest"),
+            Ok((
+                "rest",
+                (
+                    HeadingLevel::Heading1,
+                    vec![MarkdownInline::Plaintext("Title".into())]
+                )
+            ))
+        );
+        assert_eq!(
+            parse_setext_heading("*Sub*\n  title  \n   -  \n"),
+            Ok((
+                "",
+                (
+                    HeadingLevel::Heading2,
+                    vec![
+                        MarkdownInline::Italic(vec![MarkdownInline::Plaintext("Sub".into())]),
+                        MarkdownInline::SoftBreak,
+                        MarkdownInline::Plaintext("title".into()),
+                    ]
+                )
+            ))
+        );
+        assert_eq!(
+            parse_setext_heading("Title\n\n==="),
+            Err(NomErr::Error(Error {
+                input: "\n===",
+                code: ErrorKind::TakeWhile1
+            }))
+        );
+        assert_eq!(
+            parse_setext_underline("= ="),
+            Err(NomErr::Error(Error {
+                input: "=",
+                code: ErrorKind::Eof
+            }))
+        );
+        assert_eq!(
+            parse_markdown("===\n\ntext\n---\n---\n"),
+            Ok((
+                "",
+                vec![
+                    Markdown::Paragraph(vec![MarkdownInline::Plaintext("===".into())]),
+                    Markdown::Heading(
+                        HeadingLevel::Heading2,
+                        vec![MarkdownInline::Plaintext("text".into())]
+                    ),
+                    Markdown::ThematicBreak,
+                ]
+            ))
+        );
+        assert_eq!(
+            try_parse_markdown("> a\n===\n"),
+            Ok(vec![Markdown::BlockQuote(vec![Markdown::Paragraph(vec![
+                MarkdownInline::Plaintext("a".into()),
+                MarkdownInline::SoftBreak,
+                MarkdownInline::Plaintext("===".into()),
+            ])])])
+        );
+        assert_eq!(
+            render_markdown("- a\n==\n"),
+            String::from("<ul><li>a\n==</li></ul>")
+        );
+        assert_eq!(
+            render_markdown("- a\n-\n- c\n"),
+            String::from("<ul><li>a</li><li></li><li>c</li></ul>")
+        );
+        assert_eq!(render_markdown("a\n-\n"), String::from("<h2>a</h2>"));
+    }
+
+    #[test]
+    fn test_parse_unordered_list_tag() {
+        assert_eq!(parse_unordered_list_tag("- "), Ok(("", "-")));
+        assert_eq!(
+            parse_unordered_list_tag("- and some more"),
+            Ok(("and some more", "-"))
+        );
+        assert_eq!(parse_unordered_list_tag("-"), Ok(("", "-")));
+        assert_eq!(parse_unordered_list_tag("-\nb"), Ok(("\nb", "-")));
+        assert_eq!(
+            parse_unordered_list_tag("-and some more"),
+            Err(NomErr::Error(Error {
+                input: "and some more",
+                code: ErrorKind::Eof
+            }))
+        );
+        assert_eq!(
+            parse_unordered_list_tag("--"),
+            Err(NomErr::Error(Error {
+                input: "-",
+                code: ErrorKind::Eof
+            }))
         );
         assert_eq!(
             parse_unordered_list_tag(""),
             Err(NomErr::Error(Error {
                 input: "",
-                code: ErrorKind::Tag
+                code: ErrorKind::OneOf
+            }))
+        );
+        assert_eq!(parse_unordered_list_tag("* a"), Ok(("a", "*")));
+        assert_eq!(parse_unordered_list_tag("+ a"), Ok(("a", "+")));
+        assert_eq!(parse_unordered_list_tag("   - a"), Ok(("a", "-")));
+        assert_eq!(
+            parse_unordered_list_tag("    - a"),
+            Err(NomErr::Error(Error {
+                input: " - a",
+                code: ErrorKind::OneOf
             }))
         );
     }
@@ -747,7 +2400,13 @@ mod tests {
     fn test_parse_unordered_list_element() {
         assert_eq!(
             parse_unordered_list_element("- this is an element\n"),
-            Ok(("", vec![MarkdownInline::Plaintext("this is an element")]))
+            Ok((
+                "",
+                vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "this is an element".into()
+                )])]
+                .into()
+            ))
         );
         assert_eq!(
             parse_unordered_list_element(
@@ -757,54 +2416,57 @@ mod tests {
             ),
             Ok((
                 "- this is another element\n",
-                vec![MarkdownInline::Plaintext("this is an element")]
+                vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "this is an element".into()
+                )])]
+                .into()
             ))
         );
         assert_eq!(
             parse_unordered_list_element(""),
             Err(NomErr::Error(Error {
                 input: "",
-                code: ErrorKind::Tag
+                code: ErrorKind::OneOf
             }))
         );
-        assert_eq!(parse_unordered_list_element("- \n"), Ok(("", vec![])));
         assert_eq!(
-            parse_unordered_list_element("- "),
-            Err(NomErr::Error(Error {
-                input: "",
-                code: ErrorKind::Tag
-            }))
+            parse_unordered_list_element("- \n"),
+            Ok(("", vec![].into()))
         );
+        assert_eq!(parse_unordered_list_element("- "), Ok(("", vec![].into())));
         assert_eq!(
             parse_unordered_list_element("- test"),
-            Err(NomErr::Error(Error {
-                input: "",
-                code: ErrorKind::Tag
-            }))
-        );
-        assert_eq!(
-            parse_unordered_list_element("-"),
-            Err(NomErr::Error(Error {
-                input: "",
-                code: ErrorKind::Tag
-            }))
+            Ok((
+                "",
+                vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "test".into()
+                )])]
+                .into()
+            ))
         );
+        assert_eq!(parse_unordered_list_element("-"), Ok(("", vec![].into())));
     }
 
     #[test]
     fn test_parse_unordered_list() {
         assert_eq!(
             parse_unordered_list("- this is an element"),
-            Err(NomErr::Error(Error {
-                input: "",
-                code: ErrorKind::Tag
-            }))
+            Ok((
+                "",
+                vec![vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "this is an element".into()
+                )])]
+                .into()]
+            ))
         );
         assert_eq!(
             parse_unordered_list("- this is an element\n"),
             Ok((
                 "",
-                vec![vec![MarkdownInline::Plaintext("this is an element")]]
+                vec![vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "this is an element".into()
+                )])]
+                .into()]
             ))
         );
         assert_eq!(
@@ -816,8 +2478,37 @@ mod tests {
             Ok((
                 "",
                 vec![
-                    vec![MarkdownInline::Plaintext("this is an element")],
-                    vec![MarkdownInline::Plaintext("here is another")]
+                    vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                        "this is an element".into()
+                    )])]
+                    .into(),
+                    vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                        "here is another".into()
+                    )])]
+                    .into()
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_markdown("- a\n* b\n* c\n- - -\n"),
+            Ok((
+                "",
+                vec![
+                    Markdown::UnorderedList(vec![vec![Markdown::Paragraph(vec![
+                        MarkdownInline::Plaintext("a".into())
+                    ])]
+                    .into()]),
+                    Markdown::UnorderedList(vec![
+                        vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                            "b".into()
+                        )])]
+                        .into(),
+                        vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                            "c".into()
+                        )])]
+                        .into(),
+                    ]),
+                    Markdown::ThematicBreak,
                 ]
             ))
         );
@@ -842,16 +2533,10 @@ mod tests {
             parse_ordered_list_tag("1.and some more"),
             Err(NomErr::Error(Error {
                 input: "and some more",
-                code: ErrorKind::Tag
-            }))
-        );
-        assert_eq!(
-            parse_ordered_list_tag("1111."),
-            Err(NomErr::Error(Error {
-                input: "",
-                code: ErrorKind::Tag
+                code: ErrorKind::Eof
             }))
         );
+        assert_eq!(parse_ordered_list_tag("1111."), Ok(("", "1111")));
         assert_eq!(
             parse_ordered_list_tag(""),
             Err(NomErr::Error(Error {
@@ -865,7 +2550,13 @@ mod tests {
     fn test_parse_ordered_list_element() {
         assert_eq!(
             parse_ordered_list_element("1. this is an element\n"),
-            Ok(("", vec![MarkdownInline::Plaintext("this is an element")]))
+            Ok((
+                "",
+                vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "this is an element".into()
+                )])]
+                .into()
+            ))
         );
         assert_eq!(
             parse_ordered_list_element(
@@ -875,7 +2566,10 @@ mod tests {
             ),
             Ok((
                 "1. here is another\n",
-                vec![MarkdownInline::Plaintext("this is an element")]
+                vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "this is an element".into()
+                )])]
+                .into()
             ))
         );
         assert_eq!(
@@ -892,28 +2586,19 @@ mod tests {
                 code: ErrorKind::TakeWhile1
             }))
         );
-        assert_eq!(parse_ordered_list_element("1. \n"), Ok(("", vec![])));
+        assert_eq!(parse_ordered_list_element("1. \n"), Ok(("", vec![].into())));
         assert_eq!(
             parse_ordered_list_element("1. test"),
-            Err(NomErr::Error(Error {
-                input: "",
-                code: ErrorKind::Tag
-            }))
-        );
-        assert_eq!(
-            parse_ordered_list_element("1. "),
-            Err(NomErr::Error(Error {
-                input: "",
-                code: ErrorKind::Tag
-            }))
-        );
-        assert_eq!(
-            parse_ordered_list_element("1."),
-            Err(NomErr::Error(Error {
-                input: "",
-                code: ErrorKind::Tag
-            }))
+            Ok((
+                "",
+                vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "test".into()
+                )])]
+                .into()
+            ))
         );
+        assert_eq!(parse_ordered_list_element("1. "), Ok(("", vec![].into())));
+        assert_eq!(parse_ordered_list_element("1."), Ok(("", vec![].into())));
     }
 
     #[test]
@@ -922,15 +2607,21 @@ mod tests {
             parse_ordered_list("1. this is an element\n"),
             Ok((
                 "",
-                vec![vec![MarkdownInline::Plaintext("this is an element")]]
+                vec![vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "this is an element".into()
+                )])]
+                .into()]
             ))
         );
         assert_eq!(
             parse_ordered_list("1. test"),
-            Err(NomErr::Error(Error {
-                input: "",
-                code: ErrorKind::Tag
-            }))
+            Ok((
+                "",
+                vec![vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "test".into()
+                )])]
+                .into()]
+            ))
         );
         assert_eq!(
             parse_ordered_list(
@@ -941,8 +2632,14 @@ mod tests {
             Ok((
                 "",
                 vec![
-                    vec!(MarkdownInline::Plaintext("this is an element")),
-                    vec![MarkdownInline::Plaintext("here is another")]
+                    vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                        "this is an element".into()
+                    )])]
+                    .into(),
+                    vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                        "here is another".into()
+                    )])]
+                    .into()
                 ]
             ))
         );
@@ -958,11 +2655,13 @@ pip install foobar
             ),
             Ok((
                 "",
-                (
-                    "bash",
-                    r#"pip install foobar
+                CodeBlock {
+                    lang: Some("bash".into()),
+                    attributes: None,
+                    body: r#"pip install foobar
 "#
-                )
+                    .into()
+                }
             ))
         );
         assert_eq!(
@@ -977,21 +2676,96 @@ foobar.singularize('phenomena') # returns 'phenomenon'
             ),
             Ok((
                 "",
-                (
-                    "python",
-                    r#"import foobar
+                CodeBlock {
+                    lang: Some("python".into()),
+                    attributes: None,
+                    body: r#"import foobar
 
 foobar.pluralize('word') # returns 'words'
 foobar.pluralize('goose') # returns 'geese'
 foobar.singularize('phenomena') # returns 'phenomenon'
 "#
-                )
+                    .into()
+                }
+            ))
+        );
+        assert_eq!(
+            parse_code_block("```bash\n pip `install` foobar\n```"),
+            Ok((
+                "",
+                CodeBlock {
+                    lang: Some("bash".into()),
+                    attributes: None,
+                    body: " pip `install` foobar\n".into()
+                }
+            ))
+        );
+        assert_eq!(
+            parse_code_block("~~~~md title=\"README.md\"  \n```rust\n~~~\n```\n~~~~~ \nrest"),
+            Ok((
+                "rest",
+                CodeBlock {
+                    lang: Some("md".into()),
+                    attributes: Some("title=\"README.md\"".into()),
+                    body: "```rust\n~~~\n```\n".into()
+                }
+            ))
+        );
+        assert_eq!(
+            parse_code_block("  ```\n    a\n   b\n c\n   ```"),
+            Ok((
+                "",
+                CodeBlock {
+                    lang: None,
+                    attributes: None,
+                    body: "  a\n b\nc\n".into()
+                }
+            ))
+        );
+        assert_eq!(
+            parse_code_block("```\n```"),
+            Ok((
+                "",
+                CodeBlock {
+                    lang: None,
+                    attributes: None,
+                    body: "".into()
+                }
             ))
         );
-        // assert_eq!(
-        // 	parse_code_block("```bash\n pip `install` foobar\n```"),
-        // 	Ok(("", "bash\n pip `install` foobar\n"))
-        // );
+        assert_eq!(
+            parse_code_block("````\ncode\n```\n"),
+            Ok((
+                "",
+                CodeBlock {
+                    lang: None,
+                    attributes: None,
+                    body: "code\n```\n".into()
+                }
+            ))
+        );
+        assert_eq!(
+            parse_code_block("``` a`b\n```"),
+            Err(NomErr::Error(Error {
+                input: " a`b\n```",
+                code: ErrorKind::Verify
+            }))
+        );
+        assert_eq!(
+            try_parse_markdown("> ```\n> x\n\nafter\n"),
+            Ok(vec![
+                Markdown::BlockQuote(vec![Markdown::Codeblock(CodeBlock {
+                    lang: None,
+                    attributes: None,
+                    body: "x\n".into()
+                })]),
+                Markdown::Paragraph(vec![MarkdownInline::Plaintext("after".into())]),
+            ])
+        );
+        assert_eq!(
+            render_markdown("- item\n  ```\n  code\n- next\n"),
+            String::from("<ul><li>item<pre><code>code\n</code></pre></li><li>next</li></ul>")
+        );
     }
 
     #[test]
@@ -1004,69 +2778,1337 @@ pip install foobar
             ),
             Ok((
                 "",
-                (
-                    "__UNKNOWN_LANGUAGE__",
-                    r#"pip install foobar
+                CodeBlock {
+                    lang: None,
+                    attributes: None,
+                    body: r#"pip install foobar
 "#
-                )
+                    .into()
+                }
             ))
         );
     }
 
     #[test]
-    fn test_parse_markdown() {
+    fn test_parse_indented_code_block() {
         assert_eq!(
-            parse_markdown(
-                r#"# Foobar
-
-Foobar is a Python library for dealing with word pluralization.
-
-```bash
-pip install foobar
-```
-## Installation
-
-Use the package manager [pip](https://pip.pypa.io/en/stable/) to install foobar.
-```python
-import foobar
-
-foobar.pluralize('word') # returns 'words'
-foobar.pluralize('goose') # returns 'geese'
-foobar.singularize('phenomena') # returns 'phenomenon'
-```"#
-            ),
+            parse_indented_code_block("    a\n  \n      b\n\n\tc\n\nnext"),
+            Ok((
+                "\nnext",
+                CodeBlock {
+                    lang: None,
+                    attributes: None,
+                    body: "a\n\n  b\n\nc\n".into()
+                }
+            ))
+        );
+        assert_eq!(
+            parse_indented_code_block("   a"),
+            Err(NomErr::Error(Error {
+                input: "a",
+                code: ErrorKind::Tag
+            }))
+        );
+        assert_eq!(
+            parse_markdown("text\n    more text\n\n    code\n- item\n\n      item code\n"),
             Ok((
                 "",
                 vec![
-                    Markdown::Heading(1.into(), vec![MarkdownInline::Plaintext("Foobar")]),
-                    Markdown::Line(vec![]),
-                    Markdown::Line(vec![MarkdownInline::Plaintext(
-                        "Foobar is a Python library for dealing with word pluralization."
-                    )]),
-                    Markdown::Line(vec![]),
-                    Markdown::Codeblock("bash", "pip install foobar\n"),
-                    Markdown::Line(vec![]),
-                    Markdown::Heading(
-                        HeadingLevel::Heading2,
-                        vec![MarkdownInline::Plaintext("Installation")]
-                    ),
-                    Markdown::Line(vec![]),
-                    Markdown::Line(vec![
-                        MarkdownInline::Plaintext("Use the package manager "),
-                        MarkdownInline::Link(("pip", "https://pip.pypa.io/en/stable/")),
-                        MarkdownInline::Plaintext(" to install foobar."),
+                    Markdown::Paragraph(vec![
+                        MarkdownInline::Plaintext("text".into()),
+                        MarkdownInline::SoftBreak,
+                        MarkdownInline::Plaintext("more text".into()),
                     ]),
-                    Markdown::Codeblock(
-                        "python",
-                        r#"import foobar
-
-foobar.pluralize('word') # returns 'words'
-foobar.pluralize('goose') # returns 'geese'
-foobar.singularize('phenomena') # returns 'phenomenon'
-"#
-                    ),
+                    Markdown::Codeblock(CodeBlock {
+                        lang: None,
+                        attributes: None,
+                        body: "code\n".into()
+                    }),
+                    Markdown::UnorderedList(vec![vec![
+                        Markdown::Paragraph(vec![MarkdownInline::Plaintext("item".into())]),
+                        Markdown::Codeblock(CodeBlock {
+                            lang: None,
+                            attributes: None,
+                            body: "item code\n".into()
+                        }),
+                    ]
+                    .into()]),
                 ]
             ))
-        )
+        );
+    }
+
+    #[test]
+    fn test_parse_front_matter() {
+        assert_eq!(
+            parse_front_matter("---\ntitle: Hi\n...\n# Hi"),
+            Ok((
+                "# Hi",
+                FrontMatter {
+                    format: FrontMatterFormat::Yaml,
+                    raw: "title: Hi\n".into(),
+                    fields: HashMap::from([(String::from("title"), String::from("Hi"))])
+                }
+            ))
+        );
+        assert_eq!(
+            parse_front_matter("+++\n+++"),
+            Ok((
+                "",
+                FrontMatter {
+                    format: FrontMatterFormat::Toml,
+                    raw: "".into(),
+                    fields: HashMap::new()
+                }
+            ))
+        );
+        assert_eq!(
+            parse_front_matter("---\ra: 1\rb: 2\r\n---"),
+            Ok((
+                "",
+                FrontMatter {
+                    format: FrontMatterFormat::Yaml,
+                    raw: "a: 1\nb: 2\n".into(),
+                    fields: HashMap::from([
+                        (String::from("a"), String::from("1")),
+                        (String::from("b"), String::from("2")),
+                    ])
+                }
+            ))
+        );
+        assert_eq!(
+            parse_front_matter("---\ntitle: Hi\n+++\n"),
+            Err(NomErr::Error(Error {
+                input: "---\ntitle: Hi\n+++\n",
+                code: ErrorKind::Fail
+            }))
+        );
+        assert_eq!(
+            parse_markdown("> ---\n> a\n> ---\n"),
+            Ok((
+                "",
+                vec![Markdown::BlockQuote(vec![
+                    Markdown::ThematicBreak,
+                    Markdown::Heading(
+                        HeadingLevel::Heading2,
+                        vec![MarkdownInline::Plaintext("a".into())]
+                    ),
+                ])]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_markdown() {
+        assert_eq!(
+            parse_markdown(
+                r#"# Foobar
+
+Foobar is a Python library for dealing with word pluralization.
+
+```bash
+pip install foobar
+```
+## Installation
+
+Use the package manager [pip](https://pip.pypa.io/en/stable/) to install foobar.
+```python
+import foobar
+
+foobar.pluralize('word') # returns 'words'
+foobar.pluralize('goose') # returns 'geese'
+foobar.singularize('phenomena') # returns 'phenomenon'
+```"#
+            ),
+            Ok((
+                "",
+                vec![
+                    Markdown::Heading(
+                        HeadingLevel::Heading1,
+                        vec![MarkdownInline::Plaintext("Foobar".into())]
+                    ),
+                    Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                        "Foobar is a Python library for dealing with word pluralization.".into()
+                    )]),
+                    Markdown::Codeblock(CodeBlock {
+                        lang: Some("bash".into()),
+                        attributes: None,
+                        body: "pip install foobar\n".into()
+                    }),
+                    Markdown::Heading(
+                        HeadingLevel::Heading2,
+                        vec![MarkdownInline::Plaintext("Installation".into())]
+                    ),
+                    Markdown::Paragraph(vec![
+                        MarkdownInline::Plaintext("Use the package manager ".into()),
+                        MarkdownInline::Link(Link {
+                            text: vec![MarkdownInline::Plaintext("pip".into())],
+                            url: "https://pip.pypa.io/en/stable/".into(),
+                            title: None
+                        }),
+                        MarkdownInline::Plaintext(" to install foobar.".into()),
+                    ]),
+                    Markdown::Codeblock(CodeBlock {
+                        lang: Some("python".into()),
+                        attributes: None,
+                        body: r#"import foobar
+
+foobar.pluralize('word') # returns 'words'
+foobar.pluralize('goose') # returns 'geese'
+foobar.singularize('phenomena') # returns 'phenomenon'
+"#
+                        .into()
+                    }),
+                ]
+            ))
+        )
+    }
+
+    #[test]
+    fn test_try_parse_markdown() {
+        assert_eq!(
+            try_parse_markdown("# hi\n"),
+            Ok(vec![Markdown::Heading(
+                HeadingLevel::Heading1,
+                vec![MarkdownInline::Plaintext("hi".into())]
+            )])
+        );
+        assert_eq!(try_parse_markdown(""), Ok(vec![]));
+        assert_eq!(
+            try_parse_markdown("# hi\nthere"),
+            Ok(vec![
+                Markdown::Heading(
+                    HeadingLevel::Heading1,
+                    vec![MarkdownInline::Plaintext("hi".into())]
+                ),
+                Markdown::Paragraph(vec![MarkdownInline::Plaintext("there".into())]),
+            ])
+        );
+        assert_eq!(
+            try_parse_markdown("fine\nthis is `not closed\nfine\n"),
+            Ok(vec![Markdown::Paragraph(vec![
+                MarkdownInline::Plaintext("fine".into()),
+                MarkdownInline::SoftBreak,
+                MarkdownInline::Plaintext("this is `not closed".into()),
+                MarkdownInline::SoftBreak,
+                MarkdownInline::Plaintext("fine".into()),
+            ])])
+        );
+        assert_eq!(
+            try_parse_markdown("- ``code\n"),
+            Ok(vec![Markdown::UnorderedList(vec![vec![
+                Markdown::Paragraph(vec![MarkdownInline::Plaintext("``code".into())])
+            ]
+            .into()])])
+        );
+        assert_eq!(
+            try_parse_markdown("see [this\n"),
+            Ok(vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                "see [this".into()
+            )])])
+        );
+        assert_eq!(
+            render_markdown("x ![a](b\n"),
+            String::from("<p>x ![a](b</p>")
+        );
+        assert_eq!(
+            try_parse_markdown("```rust\nfn main() {}"),
+            Ok(vec![Markdown::Codeblock(CodeBlock {
+                lang: Some("rust".into()),
+                attributes: None,
+                body: "fn main() {}\n".into()
+            })])
+        );
+        assert_eq!(
+            try_parse_markdown("# Code\n\n  ````\n```\n"),
+            Ok(vec![
+                Markdown::Heading(
+                    HeadingLevel::Heading1,
+                    vec![MarkdownInline::Plaintext("Code".into())]
+                ),
+                Markdown::Codeblock(CodeBlock {
+                    lang: None,
+                    attributes: None,
+                    body: "```\n".into()
+                }),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_render_markdown() {
+        assert_eq!(render_markdown("# hi\n"), String::from("<h1>hi</h1>"));
+        assert_eq!(
+            try_render_markdown("# hi\n"),
+            Ok(String::from("<h1>hi</h1>"))
+        );
+        assert_eq!(render_markdown("a `b\n"), String::from("<p>a `b</p>"));
+        assert_eq!(
+            render_markdown("a\n* * *\n* b\n"),
+            String::from("<p>a</p><hr /><ul><li>b</li></ul>")
+        );
+    }
+
+    #[test]
+    fn test_parse_eol() {
+        assert_eq!(parse_eol("\n"), Ok(("", "\n")));
+        assert_eq!(parse_eol("\nmore"), Ok(("more", "\n")));
+        assert_eq!(parse_eol(""), Ok(("", "")));
+        assert_eq!(
+            parse_eol("more"),
+            Err(NomErr::Error(Error {
+                input: "more",
+                code: ErrorKind::Eof
+            }))
+        );
+    }
+
+    #[test]
+    fn test_parse_markdown_without_trailing_newline() {
+        assert_eq!(
+            parse_markdown("# Foobar"),
+            Ok((
+                "",
+                vec![Markdown::Heading(
+                    HeadingLevel::Heading1,
+                    vec![MarkdownInline::Plaintext("Foobar".into())]
+                )]
+            ))
+        );
+        assert_eq!(
+            parse_markdown("- one\n- two"),
+            Ok((
+                "",
+                vec![Markdown::UnorderedList(vec![
+                    vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                        "one".into()
+                    )])]
+                    .into(),
+                    vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                        "two".into()
+                    )])]
+                    .into(),
+                ])]
+            ))
+        );
+        assert_eq!(
+            parse_markdown("1. one\n2. two"),
+            Ok((
+                "",
+                vec![Markdown::OrderedList(vec![
+                    vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                        "one".into()
+                    )])]
+                    .into(),
+                    vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                        "two".into()
+                    )])]
+                    .into(),
+                ])]
+            ))
+        );
+        assert_eq!(
+            parse_markdown("some **text**\nthe end"),
+            Ok((
+                "",
+                vec![Markdown::Paragraph(vec![
+                    MarkdownInline::Plaintext("some ".into()),
+                    MarkdownInline::Bold(vec![MarkdownInline::Plaintext("text".into())]),
+                    MarkdownInline::SoftBreak,
+                    MarkdownInline::Plaintext("the end".into()),
+                ])]
+            ))
+        );
+        assert_eq!(
+            parse_markdown("```bash\npip install foobar\n```\nthe end"),
+            Ok((
+                "",
+                vec![
+                    Markdown::Codeblock(CodeBlock {
+                        lang: Some("bash".into()),
+                        attributes: None,
+                        body: "pip install foobar\n".into()
+                    }),
+                    Markdown::Paragraph(vec![MarkdownInline::Plaintext("the end".into())]),
+                ]
+            ))
+        );
+        assert_eq!(parse_markdown(""), Ok(("", vec![])));
+    }
+
+    #[test]
+    fn test_parse_line_ending() {
+        assert_eq!(parse_line_ending("\n"), Ok(("", "\n")));
+        assert_eq!(parse_line_ending("\r\n"), Ok(("", "\r\n")));
+        assert_eq!(parse_line_ending("\r"), Ok(("", "\r")));
+        assert_eq!(parse_line_ending("\r\r\n"), Ok(("\r\n", "\r")));
+        assert_eq!(parse_eol("\r\nmore"), Ok(("more", "\r\n")));
+        assert_eq!(
+            parse_line_ending(""),
+            Err(NomErr::Error(Error {
+                input: "",
+                code: ErrorKind::Tag
+            }))
+        );
+    }
+
+    #[test]
+    fn test_normalize_line_endings() {
+        assert_eq!(normalize_line_endings("a\nb\n"), Cow::Borrowed("a\nb\n"));
+        assert_eq!(
+            normalize_line_endings("a\r\nb\rc\r\r\n"),
+            Cow::<str>::Owned(String::from("a\nb\nc\n\n"))
+        );
+    }
+
+    #[test]
+    fn test_parse_markdown_line_endings() {
+        let md = r#"# Foobar
+
+Foobar is a *Python* library for dealing with word pluralization.
+```bash
+pip install foobar
+
+pip install --upgrade foobar
+```
+## Installation
+- one
+- two
+1. three
+2. four
+the end"#;
+        let expected = parse_markdown(md);
+        assert_eq!(expected.as_ref().map(|(rest, _)| *rest), Ok(""));
+        assert_eq!(parse_markdown(&md.replace('\n', "\r\n")), expected);
+        assert_eq!(parse_markdown(&md.replace('\n', "\r")), expected);
+        assert_eq!(
+            parse_markdown(&format!("{}\r\n", md.replace('\n', "\r\n"))),
+            expected
+        );
+        assert_eq!(
+            parse_heading("# h1\r\n"),
+            Ok((
+                "",
+                (
+                    HeadingLevel::Heading1,
+                    vec![MarkdownInline::Plaintext("h1".into())]
+                )
+            ))
+        );
+        assert_eq!(
+            parse_unordered_list("- a\r- b\r"),
+            Ok((
+                "",
+                vec![
+                    vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                        "a".into()
+                    )])]
+                    .into(),
+                    vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                        "b".into()
+                    )])]
+                    .into()
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_block_quote_line() {
+        assert_eq!(parse_block_quote_line("> quoted\n"), Ok(("", "quoted")));
+        assert_eq!(parse_block_quote_line(">quoted"), Ok(("", "quoted")));
+        assert_eq!(parse_block_quote_line("   > quoted"), Ok(("", "quoted")));
+        assert_eq!(parse_block_quote_line(">  quoted"), Ok(("", " quoted")));
+        assert_eq!(parse_block_quote_line("> > quoted"), Ok(("", "> quoted")));
+        assert_eq!(parse_block_quote_line(">\r\nmore"), Ok(("more", "")));
+        assert_eq!(
+            parse_block_quote_line("    > code"),
+            Err(NomErr::Error(Error {
+                input: " > code",
+                code: ErrorKind::Tag
+            }))
+        );
+        assert_eq!(
+            parse_block_quote_line("quoted"),
+            Err(NomErr::Error(Error {
+                input: "quoted",
+                code: ErrorKind::Tag
+            }))
+        );
+    }
+
+    #[test]
+    fn test_parse_lazy_continuation_line() {
+        assert_eq!(
+            parse_lazy_continuation_line("more text\n"),
+            Ok(("", "more text"))
+        );
+        assert_eq!(
+            parse_lazy_continuation_line("\n"),
+            Err(NomErr::Error(Error {
+                input: "\n",
+                code: ErrorKind::Not
+            }))
+        );
+        assert_eq!(
+            parse_lazy_continuation_line(""),
+            Err(NomErr::Error(Error {
+                input: "",
+                code: ErrorKind::Not
+            }))
+        );
+        assert_eq!(
+            parse_lazy_continuation_line("- item\n"),
+            Err(NomErr::Error(Error {
+                input: "- item\n",
+                code: ErrorKind::Not
+            }))
+        );
+        assert_eq!(
+            parse_lazy_continuation_line("# heading\n"),
+            Err(NomErr::Error(Error {
+                input: "# heading\n",
+                code: ErrorKind::Not
+            }))
+        );
+        assert_eq!(
+            parse_lazy_continuation_line("-\n"),
+            Err(NomErr::Error(Error {
+                input: "-\n",
+                code: ErrorKind::Not
+            }))
+        );
+        assert_eq!(parse_lazy_continuation_line("===\n"), Ok(("", "===")));
+    }
+
+    #[test]
+    fn test_parse_block_quote() {
+        assert_eq!(
+            parse_block_quote("> quoted\n> **text**\n"),
+            Ok((
+                "",
+                vec![Markdown::Paragraph(vec![
+                    MarkdownInline::Plaintext("quoted".into()),
+                    MarkdownInline::SoftBreak,
+                    MarkdownInline::Bold(vec![MarkdownInline::Plaintext("text".into())]),
+                ])]
+            ))
+        );
+        assert_eq!(
+            parse_block_quote("> quoted\nlazy\n\nnot quoted\n"),
+            Ok((
+                "\nnot quoted\n",
+                vec![Markdown::Paragraph(vec![
+                    MarkdownInline::Plaintext("quoted".into()),
+                    MarkdownInline::SoftBreak,
+                    MarkdownInline::Plaintext("lazy".into()),
+                ])]
+            ))
+        );
+        assert_eq!(
+            parse_block_quote("> # heading\nnot lazy\n"),
+            Ok((
+                "not lazy\n",
+                vec![Markdown::Heading(
+                    HeadingLevel::Heading1,
+                    vec![MarkdownInline::Plaintext("heading".into())]
+                )]
+            ))
+        );
+        assert_eq!(
+            parse_block_quote("> quoted\n- not lazy\n"),
+            Ok((
+                "- not lazy\n",
+                vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "quoted".into()
+                )])]
+            ))
+        );
+        assert_eq!(
+            parse_block_quote("> ```\n> code\nnot lazy\n"),
+            Ok((
+                "not lazy\n",
+                vec![Markdown::Codeblock(CodeBlock {
+                    lang: None,
+                    attributes: None,
+                    body: "code\n".into()
+                })]
+            ))
+        );
+        assert_eq!(
+            parse_block_quote("> ```rust\n> let a = 1;\n> ```\n> the end"),
+            Ok((
+                "",
+                vec![
+                    Markdown::Codeblock(CodeBlock {
+                        lang: Some("rust".into()),
+                        attributes: None,
+                        body: "let a = 1;\n".into()
+                    }),
+                    Markdown::Paragraph(vec![MarkdownInline::Plaintext("the end".into())]),
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_block_quote("quoted"),
+            Err(NomErr::Error(Error {
+                input: "quoted",
+                code: ErrorKind::Tag
+            }))
+        );
+    }
+
+    #[test]
+    fn test_parse_nested_block_quote() {
+        assert_eq!(
+            parse_block_quote("> outer\n>> inner\n> > lazy inner\nlazy\n> - item\n"),
+            Ok((
+                "",
+                vec![
+                    Markdown::Paragraph(vec![MarkdownInline::Plaintext("outer".into())]),
+                    Markdown::BlockQuote(vec![Markdown::Paragraph(vec![
+                        MarkdownInline::Plaintext("inner".into()),
+                        MarkdownInline::SoftBreak,
+                        MarkdownInline::Plaintext("lazy inner".into()),
+                        MarkdownInline::SoftBreak,
+                        MarkdownInline::Plaintext("lazy".into()),
+                    ])]),
+                    Markdown::UnorderedList(vec![vec![Markdown::Paragraph(vec![
+                        MarkdownInline::Plaintext("item".into())
+                    ])]
+                    .into()]),
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_markdown("> a\r\n> > b\r\n"),
+            Ok((
+                "",
+                vec![Markdown::BlockQuote(vec![
+                    Markdown::Paragraph(vec![MarkdownInline::Plaintext("a".into())]),
+                    Markdown::BlockQuote(vec![Markdown::Paragraph(vec![
+                        MarkdownInline::Plaintext("b".into())
+                    ])]),
+                ])]
+            ))
+        );
+        assert_eq!(
+            try_parse_markdown("> fine\n> `not closed\n"),
+            Ok(vec![Markdown::BlockQuote(vec![Markdown::Paragraph(vec![
+                MarkdownInline::Plaintext("fine".into()),
+                MarkdownInline::SoftBreak,
+                MarkdownInline::Plaintext("`not closed".into()),
+            ])])])
+        );
+    }
+
+    #[test]
+    fn test_parse_deeply_nested_container_blocks() {
+        let depth = constants::MAX_NESTING_DEPTH;
+        let text = |markers: &str| format!("{}a", markers.repeat(1000 - depth)).into();
+
+        let mut expected = vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(text(
+            "> ",
+        ))])];
+        for _ in 0..depth {
+            expected = vec![Markdown::BlockQuote(expected)];
+        }
+        assert_eq!(
+            try_parse_markdown(&format!("{}a", "> ".repeat(1000))),
+            Ok(expected)
+        );
+
+        let mut expected = vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(text(
+            "- ",
+        ))])];
+        for _ in 0..depth {
+            expected = vec![Markdown::UnorderedList(vec![expected.into()])];
+        }
+        assert_eq!(
+            try_parse_markdown(&format!("{}a", "- ".repeat(1000))),
+            Ok(expected)
+        );
+    }
+
+    /// Each line of a container block is only looked at once when it is collected, so parsing
+    /// four times as many lines should take about four times as long, not sixteen. This depends on
+    /// wall-clock time, so it's only run on request: `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_parse_long_container_blocks() {
+        let time_to_parse = |md: String| {
+            let start = std::time::Instant::now();
+            assert!(try_parse_markdown(&md).is_ok());
+            start.elapsed()
+        };
+        let documents: [fn(usize) -> String; 3] = [
+            |lines| "> a\n".repeat(lines),
+            |lines| format!("- a\n{}", "b\n".repeat(lines)),
+            |lines| "> > > a\nb\n".repeat(lines / 2),
+        ];
+        for document in documents {
+            let short = time_to_parse(document(2000));
+            let long = time_to_parse(document(8000));
+            assert!(long < short * 8, "{:?} vs {:?}", short, long);
+        }
+    }
+
+    #[test]
+    fn test_parse_nested_list() {
+        assert_eq!(
+            parse_unordered_list(
+                r#"- one
+  - one.one
+    1. one.one.one
+  - one.two
+- two
+"#
+            ),
+            Ok((
+                "",
+                vec![
+                    vec![
+                        Markdown::Paragraph(vec![MarkdownInline::Plaintext("one".into())]),
+                        Markdown::UnorderedList(vec![
+                            vec![
+                                Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                                    "one.one".into()
+                                )]),
+                                Markdown::OrderedList(vec![vec![Markdown::Paragraph(vec![
+                                    MarkdownInline::Plaintext("one.one.one".into())
+                                ])]
+                                .into()]),
+                            ]
+                            .into(),
+                            vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                                "one.two".into()
+                            )])]
+                            .into(),
+                        ]),
+                    ]
+                    .into(),
+                    vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                        "two".into()
+                    )])]
+                    .into(),
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_ordered_list(
+                r#"1. one
+   - one.one
+
+   ```rust
+   let a = 1;
+   ```
+2. two
+lazy
+"#
+            ),
+            Ok((
+                "",
+                vec![
+                    vec![
+                        Markdown::Paragraph(vec![MarkdownInline::Plaintext("one".into())]),
+                        Markdown::UnorderedList(vec![vec![Markdown::Paragraph(vec![
+                            MarkdownInline::Plaintext("one.one".into())
+                        ])]
+                        .into()]),
+                        Markdown::Codeblock(CodeBlock {
+                            lang: Some("rust".into()),
+                            attributes: None,
+                            body: "let a = 1;\n".into()
+                        }),
+                    ]
+                    .into(),
+                    vec![Markdown::Paragraph(vec![
+                        MarkdownInline::Plaintext("two".into()),
+                        MarkdownInline::SoftBreak,
+                        MarkdownInline::Plaintext("lazy".into()),
+                    ])]
+                    .into(),
+                ]
+            ))
+        );
+        assert_eq!(
+            render_markdown("- a\n    - b\n"),
+            String::from("<ul><li>a<ul><li>b</li></ul></li></ul>")
+        );
+        assert_eq!(
+            render_markdown("* a\n    * b\n        * c\n"),
+            String::from("<ul><li>a<ul><li>b<ul><li>c</li></ul></li></ul></li></ul>")
+        );
+        assert_eq!(
+            render_markdown("1. a\n    - b\n"),
+            String::from("<ol><li>a<ul><li>b</li></ul></li></ol>")
+        );
+        assert_eq!(
+            render_markdown(" - a\n - b\n"),
+            String::from("<ul><li>a</li><li>b</li></ul>")
+        );
+        assert_eq!(
+            render_markdown("   - a\n\n     b\n"),
+            String::from("<ul><li><p>a</p><p>b</p></li></ul>")
+        );
+    }
+
+    #[test]
+    fn test_parse_list_blank_lines() {
+        assert_eq!(
+            parse_unordered_list("- one\n\n- two\n\nnot in the list\n"),
+            Ok((
+                "\nnot in the list\n",
+                vec![
+                    vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                        "one".into()
+                    )])]
+                    .into(),
+                    vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                        "two".into()
+                    )])]
+                    .into(),
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_unordered_list("- one\n\n  still one\n1. not in the list\n"),
+            Ok((
+                "1. not in the list\n",
+                vec![vec![
+                    Markdown::Paragraph(vec![MarkdownInline::Plaintext("one".into())]),
+                    Markdown::Paragraph(vec![MarkdownInline::Plaintext("still one".into())]),
+                ]
+                .into()]
+            ))
+        );
+        assert_eq!(
+            parse_markdown("- one\n\n1. two\n"),
+            Ok((
+                "",
+                vec![
+                    Markdown::UnorderedList(vec![vec![Markdown::Paragraph(vec![
+                        MarkdownInline::Plaintext("one".into())
+                    ])]
+                    .into()]),
+                    Markdown::OrderedList(vec![vec![Markdown::Paragraph(vec![
+                        MarkdownInline::Plaintext("two".into())
+                    ])]
+                    .into()]),
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_table_delimiter_row() {
+        assert_eq!(
+            parse_table_delimiter_row("| :--- | :-: | --: | - |\n"),
+            Ok((
+                "",
+                vec![
+                    Alignment::Left,
+                    Alignment::Center,
+                    Alignment::Right,
+                    Alignment::None
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_table_delimiter_row("--|--\nrest"),
+            Ok(("rest", vec![Alignment::None, Alignment::None]))
+        );
+        assert_eq!(
+            parse_table_delimiter_row("| - |"),
+            Ok(("", vec![Alignment::None]))
+        );
+        assert_eq!(
+            parse_table_delimiter_row("---\n"),
+            Err(NomErr::Error(Error {
+                input: "---\n",
+                code: ErrorKind::Verify
+            }))
+        );
+        assert_eq!(
+            parse_table_delimiter_row("| a |\n"),
+            Err(NomErr::Error(Error {
+                input: "a |\n",
+                code: ErrorKind::TakeWhile1
+            }))
+        );
+    }
+
+    #[test]
+    fn test_parse_table() {
+        assert_eq!(
+            parse_table("| a | *b* |\n|:--|--:|\n| `x \\| y` |\n| 1 | 2 | 3 |\n\nafter\n"),
+            Ok((
+                "\nafter\n",
+                Table {
+                    header: vec![
+                        vec![MarkdownInline::Plaintext("a".into())],
+                        vec![MarkdownInline::Italic(vec![MarkdownInline::Plaintext(
+                            "b".into()
+                        )])],
+                    ],
+                    alignments: vec![Alignment::Left, Alignment::Right],
+                    rows: vec![
+                        vec![vec![MarkdownInline::InlineCode("x | y".into())], vec![]],
+                        vec![
+                            vec![MarkdownInline::Plaintext("1".into())],
+                            vec![MarkdownInline::Plaintext("2".into())],
+                        ],
+                    ],
+                }
+            ))
+        );
+        assert_eq!(
+            parse_table("a | b\n--|--\n1 | 2\n# heading\n"),
+            Ok((
+                "# heading\n",
+                Table {
+                    header: vec![
+                        vec![MarkdownInline::Plaintext("a".into())],
+                        vec![MarkdownInline::Plaintext("b".into())],
+                    ],
+                    alignments: vec![Alignment::None, Alignment::None],
+                    rows: vec![vec![
+                        vec![MarkdownInline::Plaintext("1".into())],
+                        vec![MarkdownInline::Plaintext("2".into())],
+                    ]],
+                }
+            ))
+        );
+        assert_eq!(
+            parse_table("| a |\n| - |"),
+            Ok((
+                "",
+                Table {
+                    header: vec![vec![MarkdownInline::Plaintext("a".into())]],
+                    alignments: vec![Alignment::None],
+                    rows: vec![],
+                }
+            ))
+        );
+        assert_eq!(
+            parse_table("| a | b |\n| - |\n"),
+            Err(NomErr::Error(Error {
+                input: "| a | b |\n| - |\n",
+                code: ErrorKind::Fail
+            }))
+        );
+        assert_eq!(
+            parse_table("a\n---\n"),
+            Err(NomErr::Error(Error {
+                input: "a\n---\n",
+                code: ErrorKind::Verify
+            }))
+        );
+        assert_eq!(
+            parse_markdown("text\n|a|b|\n|-|-|\n"),
+            Ok((
+                "",
+                vec![
+                    Markdown::Paragraph(vec![MarkdownInline::Plaintext("text".into())]),
+                    Markdown::Table(Table {
+                        header: vec![
+                            vec![MarkdownInline::Plaintext("a".into())],
+                            vec![MarkdownInline::Plaintext("b".into())],
+                        ],
+                        alignments: vec![Alignment::None, Alignment::None],
+                        rows: vec![],
+                    }),
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_task_list_marker() {
+        assert_eq!(parse_task_list_marker("[ ] todo"), Ok(("todo", false)));
+        assert_eq!(parse_task_list_marker("[x] done"), Ok(("done", true)));
+        assert_eq!(parse_task_list_marker("[X]  done"), Ok(("done", true)));
+        assert_eq!(
+            parse_task_list_marker("[x]done"),
+            Err(NomErr::Error(Error {
+                input: "done",
+                code: ErrorKind::TakeWhile1
+            }))
+        );
+        assert_eq!(
+            parse_task_list_marker("[y] no"),
+            Err(NomErr::Error(Error {
+                input: "y] no",
+                code: ErrorKind::OneOf
+            }))
+        );
+    }
+
+    #[test]
+    fn test_parse_task_list() {
+        assert_eq!(
+            parse_unordered_list("- [ ] todo\n- [x] done\n- plain\n"),
+            Ok((
+                "",
+                vec![
+                    ListItem {
+                        checked: Some(false),
+                        blocks: vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                            "todo".into()
+                        )])],
+                    },
+                    ListItem {
+                        checked: Some(true),
+                        blocks: vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                            "done".into()
+                        )])],
+                    },
+                    ListItem {
+                        checked: None,
+                        blocks: vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                            "plain".into()
+                        )])],
+                    },
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_ordered_list("1. [x] one\n   more\n"),
+            Ok((
+                "",
+                vec![ListItem {
+                    checked: Some(true),
+                    blocks: vec![Markdown::Paragraph(vec![
+                        MarkdownInline::Plaintext("one".into()),
+                        MarkdownInline::SoftBreak,
+                        MarkdownInline::Plaintext("more".into()),
+                    ])],
+                }]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_paragraph() {
+        assert_eq!(
+            parse_paragraph("one *two*\n  three\n\nfour\n"),
+            Ok((
+                "\nfour\n",
+                vec![
+                    MarkdownInline::Plaintext("one ".into()),
+                    MarkdownInline::Italic(vec![MarkdownInline::Plaintext("two".into())]),
+                    MarkdownInline::SoftBreak,
+                    MarkdownInline::Plaintext("three".into()),
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_paragraph("one\n# heading\n"),
+            Ok(("# heading\n", vec![MarkdownInline::Plaintext("one".into())]))
+        );
+        assert_eq!(
+            parse_paragraph("one\n- item\n"),
+            Ok(("- item\n", vec![MarkdownInline::Plaintext("one".into())]))
+        );
+        assert_eq!(
+            parse_paragraph("one\n> quote\n"),
+            Ok(("> quote\n", vec![MarkdownInline::Plaintext("one".into())]))
+        );
+        assert_eq!(
+            parse_paragraph("one\n| a |\n| - |\n"),
+            Ok((
+                "| a |\n| - |\n",
+                vec![MarkdownInline::Plaintext("one".into())]
+            ))
+        );
+        assert_eq!(
+            parse_paragraph("one\n`not closed\n"),
+            Ok((
+                "",
+                vec![
+                    MarkdownInline::Plaintext("one".into()),
+                    MarkdownInline::SoftBreak,
+                    MarkdownInline::Plaintext("`not closed".into()),
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_paragraph("foo `bar\n  baz  ` *qux*\n\nnext"),
+            Ok((
+                "\nnext",
+                vec![
+                    MarkdownInline::Plaintext("foo ".into()),
+                    MarkdownInline::InlineCode("bar baz  ".into()),
+                    MarkdownInline::Plaintext(" ".into()),
+                    MarkdownInline::Italic(vec![MarkdownInline::Plaintext("qux".into())]),
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_paragraph("\none"),
+            Err(NomErr::Error(Error {
+                input: "\none",
+                code: ErrorKind::Not
+            }))
+        );
+        assert_eq!(
+            parse_markdown("\n\none\ntwo\n\n\nthree\n\n"),
+            Ok((
+                "",
+                vec![
+                    Markdown::Paragraph(vec![
+                        MarkdownInline::Plaintext("one".into()),
+                        MarkdownInline::SoftBreak,
+                        MarkdownInline::Plaintext("two".into()),
+                    ]),
+                    Markdown::Paragraph(vec![MarkdownInline::Plaintext("three".into())]),
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_paragraph_line_breaks() {
+        assert_eq!(
+            parse_paragraph("one  \ntwo\\\nthree \t\nfour\\"),
+            Ok((
+                "",
+                vec![
+                    MarkdownInline::Plaintext("one".into()),
+                    MarkdownInline::HardBreak,
+                    MarkdownInline::Plaintext("two".into()),
+                    MarkdownInline::HardBreak,
+                    MarkdownInline::Plaintext("three".into()),
+                    MarkdownInline::SoftBreak,
+                    MarkdownInline::Plaintext("four\\".into()),
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_paragraph("*one*  \r\n  two  "),
+            Ok((
+                "",
+                vec![
+                    MarkdownInline::Italic(vec![MarkdownInline::Plaintext("one".into())]),
+                    MarkdownInline::HardBreak,
+                    MarkdownInline::Plaintext("two".into()),
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_nested_text() {
+        assert_eq!(
+            parse_markdown_text("**see [docs](url)**"),
+            Ok((
+                "",
+                vec![MarkdownInline::Bold(vec![
+                    MarkdownInline::Plaintext("see ".into()),
+                    MarkdownInline::Link(Link {
+                        text: vec![MarkdownInline::Plaintext("docs".into())],
+                        url: "url".into(),
+                        title: None
+                    }),
+                ])]
+            ))
+        );
+        assert_eq!(
+            parse_markdown_text("*a `code` b*"),
+            Ok((
+                "",
+                vec![MarkdownInline::Italic(vec![
+                    MarkdownInline::Plaintext("a ".into()),
+                    MarkdownInline::InlineCode("code".into()),
+                    MarkdownInline::Plaintext(" b".into()),
+                ])]
+            ))
+        );
+        assert_eq!(
+            parse_markdown_inline("[**docs** `x`](/u)"),
+            Ok((
+                "",
+                MarkdownInline::Link(Link {
+                    text: vec![
+                        MarkdownInline::Bold(vec![MarkdownInline::Plaintext("docs".into())]),
+                        MarkdownInline::Plaintext(" ".into()),
+                        MarkdownInline::InlineCode("x".into()),
+                    ],
+                    url: "/u".into(),
+                    title: None
+                })
+            ))
+        );
+        assert_eq!(
+            parse_markdown_inline("[a\nb](/u)"),
+            Ok((
+                "",
+                MarkdownInline::Link(Link {
+                    text: vec![MarkdownInline::Plaintext("a\nb".into())],
+                    url: "/u".into(),
+                    title: None
+                })
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_link_in_link() {
+        let link = |text: &str, url: &str| {
+            MarkdownInline::Link(Link {
+                text: vec![MarkdownInline::Plaintext(text.to_string().into())],
+                url: url.to_string().into(),
+                title: None,
+            })
+        };
+        assert_eq!(
+            parse_markdown_text("[[x](/a)](/b)"),
+            Ok((
+                "",
+                vec![
+                    MarkdownInline::Plaintext("[".into()),
+                    link("x", "/a"),
+                    MarkdownInline::Plaintext("](/b)".into()),
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_markdown_text("[[x](/a)][ref]"),
+            Ok((
+                "",
+                vec![
+                    MarkdownInline::Plaintext("[".into()),
+                    link("x", "/a"),
+                    MarkdownInline::Plaintext("]".into()),
+                    MarkdownInline::LinkReference((
+                        vec![MarkdownInline::Plaintext("ref".into())],
+                        "ref".into(),
+                        "]".into()
+                    )),
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_markdown_text("[<https://a.com>](/b)"),
+            Ok((
+                "",
+                vec![
+                    MarkdownInline::Plaintext("[".into()),
+                    MarkdownInline::Autolink(Autolink {
+                        text: "https://a.com".into(),
+                        url: "https://a.com".into()
+                    }),
+                    MarkdownInline::Plaintext("](/b)".into()),
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_markdown_text("[x[^1]](/b)"),
+            Ok((
+                "",
+                vec![
+                    MarkdownInline::Plaintext("[x".into()),
+                    MarkdownInline::FootnoteReference(FootnoteReference {
+                        label: "1".into(),
+                        number: None,
+                        index: 0
+                    }),
+                    MarkdownInline::Plaintext("](/b)".into()),
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_markdown_text("*[[x](/a)*](/b)"),
+            Ok((
+                "",
+                vec![
+                    MarkdownInline::Italic(vec![
+                        MarkdownInline::Plaintext("[".into()),
+                        link("x", "/a"),
+                    ]),
+                    MarkdownInline::Plaintext("](/b)".into()),
+                ]
+            ))
+        );
+        assert_eq!(
+            render_markdown(&format!("{}x{}", "[".repeat(2000), "](b)".repeat(2000))),
+            format!(
+                "<p>{}<a href=\"b\">x</a>{}</p>",
+                "[".repeat(1999),
+                "](b)".repeat(1999)
+            )
+        );
+    }
+
+    #[test]
+    fn test_remove_backslash_escapes() {
+        assert_eq!(remove_backslash_escapes("/url"), Cow::Borrowed("/url"));
+        assert_eq!(remove_backslash_escapes("a\\(b\\)\\\\c"), "a(b)\\c");
+        assert_eq!(remove_backslash_escapes("C:\\dir\\"), "C:\\dir\\");
+        assert_eq!(
+            parse_markdown_text("[a](/b\\)c \"\\\"t\\\"\")"),
+            Ok((
+                "",
+                vec![MarkdownInline::Link(Link {
+                    text: vec![MarkdownInline::Plaintext("a".into())],
+                    url: "/b)c".into(),
+                    title: Some("\"t\"".into())
+                })]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_backslash_escape() {
+        assert_eq!(parse_backslash_escape("\\*a"), Ok(("a", "*")));
+        assert_eq!(parse_backslash_escape("\\\\"), Ok(("", "\\")));
+        assert_eq!(
+            parse_backslash_escape("\\a"),
+            Err(NomErr::Error(Error {
+                input: "a",
+                code: ErrorKind::Satisfy
+            }))
+        );
+        assert_eq!(
+            parse_markdown_text("\\*not\\* \\`code\\` \\[link\\](url)"),
+            Ok((
+                "",
+                vec![MarkdownInline::Plaintext("*not* `code` [link](url)".into())]
+            ))
+        );
+        assert_eq!(
+            parse_markdown_text("*\\**"),
+            Ok((
+                "",
+                vec![MarkdownInline::Italic(vec![MarkdownInline::Plaintext(
+                    "*".into()
+                )])]
+            ))
+        );
+        assert_eq!(
+            parse_paragraph("a\\\\\nb"),
+            Ok((
+                "",
+                vec![
+                    MarkdownInline::Plaintext("a\\".into()),
+                    MarkdownInline::SoftBreak,
+                    MarkdownInline::Plaintext("b".into()),
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_markdown(
+                "\\# not a heading\n\n\\- not a list\n\n1\\. not a list\n\n\\> not a quote\n"
+            ),
+            Ok((
+                "",
+                vec![
+                    Markdown::Paragraph(vec![MarkdownInline::Plaintext("# not a heading".into())]),
+                    Markdown::Paragraph(vec![MarkdownInline::Plaintext("- not a list".into())]),
+                    Markdown::Paragraph(vec![MarkdownInline::Plaintext("1. not a list".into())]),
+                    Markdown::Paragraph(vec![MarkdownInline::Plaintext("> not a quote".into())]),
+                ]
+            ))
+        );
     }
 }
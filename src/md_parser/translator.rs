@@ -1,15 +1,49 @@
 use crate::*;
 
+/// The output of [translate_with_policy].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Translation {
+    pub html: String,
+    /// Links and images whose URL was rejected by the [UrlPolicy], in document order.
+    pub rejected_urls: Vec<RejectedUrl>,
+    /// References to footnotes that don't exist, and footnotes that are never referenced, see
+    /// [find_footnote_problems].
+    pub footnote_problems: Vec<FootnoteProblem>,
+}
+
+/// Translates using the default [UrlPolicy].
 pub fn translate(md: Vec<Markdown>) -> String {
-    md.iter()
+    translate_with_policy(md, &UrlPolicy::default()).html
+}
+
+pub fn translate_with_policy(md: Vec<Markdown>, policy: &UrlPolicy) -> Translation {
+    let mut rejected_urls = vec![];
+    let md = apply_url_policy(md, policy, &mut rejected_urls);
+    Translation {
+        footnote_problems: find_footnote_problems(&md),
+        html: translate_blocks(md),
+        rejected_urls,
+    }
+}
+
+fn translate_blocks(md: Vec<Markdown>) -> String {
+    md.into_iter()
         .map(|bit| match bit {
-            Markdown::Heading(size, line) => translate_header(size, line.to_vec()),
-            Markdown::UnorderedList(lines) => translate_unordered_list(lines.to_vec()),
-            Markdown::OrderedList(lines) => translate_ordered_list(lines.to_vec()),
-            Markdown::Codeblock(lang, code) => {
-                translate_codeblock(lang.to_string(), code.to_string())
-            }
-            Markdown::Line(line) => translate_line(line.to_vec()),
+            Markdown::Heading(size, line) => translate_header(&size, line),
+            Markdown::UnorderedList(items) => translate_unordered_list(items),
+            Markdown::OrderedList(items) => translate_ordered_list(items),
+            Markdown::Codeblock(block) => translate_codeblock(
+                block.lang.as_ref().map(|it| it.to_string()),
+                block.body.to_string(),
+            ),
+            Markdown::Paragraph(text) => translate_paragraph(text),
+            Markdown::BlockQuote(blocks) => translate_block_quote(blocks),
+            Markdown::Table(table) => translate_table(table),
+            Markdown::ThematicBreak => String::from("<hr />"),
+            Markdown::LinkDefinition(_)
+            | Markdown::FootnoteDefinition(_)
+            | Markdown::FrontMatter(_) => String::new(),
+            Markdown::Footnotes(footnotes) => translate_footnotes(footnotes),
         })
         .collect::<Vec<String>>()
         .join("")
@@ -23,22 +57,76 @@ fn translate_italic(italic: String) -> String {
     format!("<i>{italic}</i>")
 }
 
+fn translate_strikethrough(text: String) -> String {
+    format!("<del>{text}</del>")
+}
+
 fn translate_inline_code(code: String) -> String {
-    format!("<code>{code}</code>")
+    format!("<code>{}</code>", escape_code(&code))
+}
+
+fn translate_link(text: String, url: String, title: Option<String>) -> String {
+    format!(
+        "<a href=\"{}\"{}>{text}</a>",
+        escape_attribute(&url),
+        translate_title(title)
+    )
 }
 
-fn translate_link(text: String, url: String) -> String {
-    format!("<a href=\"{url}\">{text}</a>")
+fn translate_image(text: String, url: String, title: Option<String>) -> String {
+    format!(
+        "<img src=\"{}\" alt=\"{}\"{} />",
+        escape_attribute(&url),
+        escape_attribute(&text),
+        translate_title(title)
+    )
 }
 
-fn translate_image(text: String, url: String) -> String {
-    format!("<img src=\"{url}\" alt=\"{text}\" />")
+/// The `title` attribute of a link or image, if it has a title.
+fn translate_title(title: Option<String>) -> String {
+    match title {
+        Some(title) => format!(" title=\"{}\"", escape_attribute(&title)),
+        None => String::new(),
+    }
 }
 
-fn translate_list_elements(lines: Vec<MarkdownText>) -> String {
-    lines
+fn translate_list_elements(items: Vec<ListItem>) -> String {
+    items
+        .into_iter()
+        .map(|item| {
+            let checkbox = match item.checked {
+                None => "",
+                Some(false) => "<input type=\"checkbox\" disabled /> ",
+                Some(true) => "<input type=\"checkbox\" disabled checked /> ",
+            };
+            format!("<li>{checkbox}{}</li>", translate_list_item(item.blocks))
+        })
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+/// Lists that contain task list items get a class, so they can be styled without bullets.
+fn translate_list_class(items: &[ListItem]) -> &'static str {
+    if items.iter().any(|item| item.checked.is_some()) {
+        " class=\"contains-task-list\""
+    } else {
+        ""
+    }
+}
+
+/// A paragraph in a list item is not wrapped in `<p>` tags, so that `- item` becomes
+/// `<li>item</li>`. Items with several paragraphs keep them, so they stay apart.
+fn translate_list_item(blocks: Vec<Markdown>) -> String {
+    let paragraphs = blocks
         .iter()
-        .map(|line| format!("<li>{}</li>", translate_text(line.to_vec())))
+        .filter(|block| matches!(block, Markdown::Paragraph(_)))
+        .count();
+    blocks
+        .into_iter()
+        .map(|block| match block {
+            Markdown::Paragraph(text) if paragraphs == 1 => translate_text(text),
+            block => translate_blocks(vec![block]),
+        })
         .collect::<Vec<String>>()
         .join("")
 }
@@ -48,50 +136,386 @@ fn translate_header(size: &HeadingLevel, text: MarkdownText) -> String {
     format!("<h{}>{}</h{}>", size, translate_text(text), size)
 }
 
-fn translate_unordered_list(lines: Vec<MarkdownText>) -> String {
-    format!("<ul>{}</ul>", translate_list_elements(lines.to_vec()))
+fn translate_unordered_list(items: Vec<ListItem>) -> String {
+    let class = translate_list_class(&items);
+    format!("<ul{class}>{}</ul>", translate_list_elements(items))
 }
 
-fn translate_ordered_list(lines: Vec<MarkdownText>) -> String {
-    format!("<ol>{}</ol>", translate_list_elements(lines.to_vec()))
+fn translate_ordered_list(items: Vec<ListItem>) -> String {
+    let class = translate_list_class(&items);
+    format!("<ol{class}>{}</ol>", translate_list_elements(items))
 }
 
 // fn translate_code(code: MarkdownText) -> String {
 //     format!("<code>{}</code>", translate_text(code))
 // }
 
-fn translate_codeblock(lang: String, code: String) -> String {
-    format!("<pre><code class=\"lang-{lang}\">{code}</code></pre>")
+/// The `class` is left out when the codeblock has no language.
+fn translate_codeblock(lang: Option<String>, code: String) -> String {
+    let class = match lang {
+        Some(lang) => format!(" class=\"lang-{}\"", escape_attribute(&lang)),
+        None => String::new(),
+    };
+    format!("<pre><code{class}>{}</code></pre>", escape_code(&code))
 }
 
-fn translate_line(text: MarkdownText) -> String {
-    let line = translate_text(text);
-    if !line.is_empty() {
-        format!("<p>{line}</p>")
-    } else {
-        line
+fn translate_block_quote(blocks: Vec<Markdown>) -> String {
+    format!("<blockquote>{}</blockquote>", translate_blocks(blocks))
+}
+
+/// The `<tbody>` is left out when the table has no rows.
+fn translate_table(table: Table) -> String {
+    let Table {
+        header,
+        alignments,
+        rows,
+    } = table;
+    let header = translate_table_row(header, &alignments, "th");
+    if rows.is_empty() {
+        return format!("<table><thead>{header}</thead></table>");
     }
+    let rows = rows
+        .into_iter()
+        .map(|row| translate_table_row(row, &alignments, "td"))
+        .collect::<Vec<String>>()
+        .join("");
+    format!("<table><thead>{header}</thead><tbody>{rows}</tbody></table>")
+}
+
+fn translate_table_row(cells: Vec<MarkdownText>, alignments: &[Alignment], tag: &str) -> String {
+    let cells = cells
+        .into_iter()
+        .zip(alignments)
+        .map(|(cell, alignment)| {
+            let align = match alignment {
+                Alignment::None => "",
+                Alignment::Left => " align=\"left\"",
+                Alignment::Center => " align=\"center\"",
+                Alignment::Right => " align=\"right\"",
+            };
+            format!("<{tag}{align}>{}</{tag}>", translate_text(cell))
+        })
+        .collect::<Vec<String>>()
+        .join("");
+    format!("<tr>{cells}</tr>")
+}
+
+fn translate_footnote_reference(number: usize, index: usize) -> String {
+    format!(
+        "<sup class=\"footnote-ref\"><a href=\"#fn-{number}\" id=\"{}\">{number}</a></sup>",
+        footnote_reference_id(number, index)
+    )
+}
+
+/// The first reference to footnote 1 is `fnref-1`, the second one is `fnref-1-2`.
+fn footnote_reference_id(number: usize, index: usize) -> String {
+    match index {
+        0 | 1 => format!("fnref-{number}"),
+        _ => format!("fnref-{number}-{index}"),
+    }
+}
+
+/// Footnotes that are never referenced are left out.
+fn translate_footnotes(footnotes: Vec<Footnote>) -> String {
+    let items = footnotes
+        .into_iter()
+        .filter(|footnote| footnote.references > 0)
+        .enumerate()
+        .map(|(index, footnote)| translate_footnote(index + 1, footnote))
+        .collect::<Vec<String>>()
+        .join("");
+    if items.is_empty() {
+        return String::new();
+    }
+    format!("<section class=\"footnotes\"><ol>{items}</ol></section>")
+}
+
+/// Links back to each reference are added to the last paragraph of the footnote.
+fn translate_footnote(number: usize, footnote: Footnote) -> String {
+    let backlinks = (1..=footnote.references)
+        .map(|index| {
+            let sup = match index {
+                1 => String::new(),
+                _ => format!("<sup>{index}</sup>"),
+            };
+            format!(
+                "<a href=\"#{}\" class=\"footnote-backref\">↩{sup}</a>",
+                footnote_reference_id(number, index)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(" ");
+    let mut blocks = footnote.blocks;
+    let html = match blocks.pop() {
+        Some(Markdown::Paragraph(text)) => format!(
+            "{}<p>{} {backlinks}</p>",
+            translate_blocks(blocks),
+            translate_text(text)
+        ),
+        Some(last) => {
+            blocks.push(last);
+            format!("{}<p>{backlinks}</p>", translate_blocks(blocks))
+        }
+        None => format!("<p>{backlinks}</p>"),
+    };
+    format!("<li id=\"fn-{number}\">{html}</li>")
+}
+
+fn translate_paragraph(text: MarkdownText) -> String {
+    format!("<p>{}</p>", translate_text(text))
 }
 
 fn translate_text(text: MarkdownText) -> String {
     text.iter()
         .map(|part| match part {
-            MarkdownInline::Bold(text) => translate_boldtext(text.to_string()),
-            MarkdownInline::Italic(text) => translate_italic(text.to_string()),
-            MarkdownInline::BoldItalic(text) => {
-                translate_italic(translate_boldtext(text.to_string()))
+            MarkdownInline::Bold(text) => translate_boldtext(translate_text(text.to_vec())),
+            MarkdownInline::Italic(text) => translate_italic(translate_text(text.to_vec())),
+            MarkdownInline::Strikethrough(text) => {
+                translate_strikethrough(translate_text(text.to_vec()))
             }
             MarkdownInline::InlineCode(code) => translate_inline_code(code.to_string()),
-            MarkdownInline::Link((text, url)) => translate_link(text.to_string(), url.to_string()),
-            MarkdownInline::Image((text, url)) => {
-                translate_image(text.to_string(), url.to_string())
+            MarkdownInline::Link(Link { text, url, title }) => translate_link(
+                translate_text(text.to_vec()),
+                url.to_string(),
+                title.as_ref().map(|it| it.to_string()),
+            ),
+            MarkdownInline::LinkReference((text, _, closing)) => {
+                format!("[{}{}", translate_text(text.to_vec()), escape_text(closing))
+            }
+            MarkdownInline::Image(Image { alt, url, title }) => translate_image(
+                alt.to_string(),
+                url.to_string(),
+                title.as_ref().map(|it| it.to_string()),
+            ),
+            MarkdownInline::Autolink(Autolink { text, url }) => {
+                translate_link(escape_text(text), url.to_string(), None)
             }
-            MarkdownInline::Plaintext(text) => text.to_string(),
+            MarkdownInline::FootnoteReference(FootnoteReference {
+                number: Some(number),
+                index,
+                ..
+            }) => translate_footnote_reference(*number, *index),
+            MarkdownInline::FootnoteReference(FootnoteReference { label, .. }) => {
+                escape_text(&format!("[^{label}]"))
+            }
+            MarkdownInline::Plaintext(text) => escape_text(text),
+            MarkdownInline::SoftBreak => String::from("\n"),
+            MarkdownInline::HardBreak => String::from("<br />"),
         })
         .collect::<Vec<String>>()
         .join("")
 }
 
+/// Drops or neutralizes the URL of every link and image that `policy` does not allow, and records
+/// them in `rejected`.
+fn apply_url_policy<'a>(
+    md: Vec<Markdown<'a>>,
+    policy: &UrlPolicy,
+    rejected: &mut Vec<RejectedUrl>,
+) -> Vec<Markdown<'a>> {
+    md.into_iter()
+        .map(|bit| match bit {
+            Markdown::Heading(size, line) => {
+                Markdown::Heading(size, apply_url_policy_to_text(line, policy, rejected))
+            }
+            Markdown::UnorderedList(items) => {
+                Markdown::UnorderedList(apply_url_policy_to_items(items, policy, rejected))
+            }
+            Markdown::OrderedList(items) => {
+                Markdown::OrderedList(apply_url_policy_to_items(items, policy, rejected))
+            }
+            Markdown::Paragraph(text) => {
+                Markdown::Paragraph(apply_url_policy_to_text(text, policy, rejected))
+            }
+            Markdown::FootnoteDefinition(FootnoteDefinition { label, blocks }) => {
+                Markdown::FootnoteDefinition(FootnoteDefinition {
+                    label,
+                    blocks: apply_url_policy(blocks, policy, rejected),
+                })
+            }
+            Markdown::Footnotes(footnotes) => Markdown::Footnotes(
+                footnotes
+                    .into_iter()
+                    .map(|footnote| Footnote {
+                        blocks: apply_url_policy(footnote.blocks, policy, rejected),
+                        ..footnote
+                    })
+                    .collect(),
+            ),
+            Markdown::Codeblock(..)
+            | Markdown::ThematicBreak
+            | Markdown::LinkDefinition(_)
+            | Markdown::FrontMatter(_) => bit,
+            Markdown::BlockQuote(blocks) => {
+                Markdown::BlockQuote(apply_url_policy(blocks, policy, rejected))
+            }
+            Markdown::Table(Table {
+                header,
+                alignments,
+                rows,
+            }) => {
+                let mut apply_to_row = |row: Vec<MarkdownText<'a>>| {
+                    row.into_iter()
+                        .map(|cell| apply_url_policy_to_text(cell, policy, rejected))
+                        .collect()
+                };
+                Markdown::Table(Table {
+                    header: apply_to_row(header),
+                    alignments,
+                    rows: rows.into_iter().map(apply_to_row).collect(),
+                })
+            }
+        })
+        .collect()
+}
+
+fn apply_url_policy_to_items<'a>(
+    items: Vec<ListItem<'a>>,
+    policy: &UrlPolicy,
+    rejected: &mut Vec<RejectedUrl>,
+) -> Vec<ListItem<'a>> {
+    items
+        .into_iter()
+        .map(|item| ListItem {
+            checked: item.checked,
+            blocks: apply_url_policy(item.blocks, policy, rejected),
+        })
+        .collect()
+}
+
+fn apply_url_policy_to_text<'a>(
+    text: MarkdownText<'a>,
+    policy: &UrlPolicy,
+    rejected: &mut Vec<RejectedUrl>,
+) -> MarkdownText<'a> {
+    let mut result = vec![];
+    for part in text {
+        match part {
+            MarkdownInline::Bold(text) => result.push(MarkdownInline::Bold(
+                apply_url_policy_to_text(text, policy, rejected),
+            )),
+            MarkdownInline::Italic(text) => result.push(MarkdownInline::Italic(
+                apply_url_policy_to_text(text, policy, rejected),
+            )),
+            MarkdownInline::Strikethrough(text) => result.push(MarkdownInline::Strikethrough(
+                apply_url_policy_to_text(text, policy, rejected),
+            )),
+            MarkdownInline::Link(Link { text, url, title }) => {
+                let text = apply_url_policy_to_text(text, policy, rejected);
+                if policy.is_allowed(&url, UrlKind::Link) {
+                    result.push(MarkdownInline::Link(Link { text, url, title }));
+                    continue;
+                }
+                rejected.push(RejectedUrl {
+                    kind: UrlKind::Link,
+                    url: url.to_string(),
+                    text: plain_text(&text),
+                });
+                match policy.on_reject {
+                    RejectAction::Drop => result.extend(text),
+                    RejectAction::Neutralize => result.push(MarkdownInline::Link(Link {
+                        text,
+                        url: NEUTRALIZED_URL.into(),
+                        title,
+                    })),
+                }
+            }
+            MarkdownInline::Image(Image { alt, url, title }) => {
+                if policy.is_allowed(&url, UrlKind::Image) {
+                    result.push(MarkdownInline::Image(Image { alt, url, title }));
+                    continue;
+                }
+                rejected.push(RejectedUrl {
+                    kind: UrlKind::Image,
+                    url: url.to_string(),
+                    text: alt.to_string(),
+                });
+                match policy.on_reject {
+                    RejectAction::Drop => result.push(MarkdownInline::Plaintext(alt)),
+                    RejectAction::Neutralize => result.push(MarkdownInline::Image(Image {
+                        alt,
+                        url: NEUTRALIZED_URL.into(),
+                        title,
+                    })),
+                }
+            }
+            MarkdownInline::Autolink(Autolink { text, url }) => {
+                if policy.is_allowed(&url, UrlKind::Link) {
+                    result.push(MarkdownInline::Autolink(Autolink { text, url }));
+                    continue;
+                }
+                rejected.push(RejectedUrl {
+                    kind: UrlKind::Link,
+                    url: url.to_string(),
+                    text: text.to_string(),
+                });
+                match policy.on_reject {
+                    RejectAction::Drop => result.push(MarkdownInline::Plaintext(text)),
+                    RejectAction::Neutralize => result.push(MarkdownInline::Autolink(Autolink {
+                        text,
+                        url: NEUTRALIZED_URL.into(),
+                    })),
+                }
+            }
+            part => result.push(part),
+        }
+    }
+    result
+}
+
+/// Returns the text in `text` without any formatting, eg: the text of a rejected link.
+fn plain_text(text: &MarkdownText) -> String {
+    text.iter()
+        .map(|part| match part {
+            MarkdownInline::Bold(text)
+            | MarkdownInline::Italic(text)
+            | MarkdownInline::Strikethrough(text)
+            | MarkdownInline::Link(Link { text, .. })
+            | MarkdownInline::LinkReference((text, ..)) => plain_text(text),
+            MarkdownInline::FootnoteReference(_) => String::new(),
+            MarkdownInline::Image(Image { alt: text, .. })
+            | MarkdownInline::Autolink(Autolink { text, .. })
+            | MarkdownInline::InlineCode(text)
+            | MarkdownInline::Plaintext(text) => text.to_string(),
+            MarkdownInline::SoftBreak | MarkdownInline::HardBreak => String::from("\n"),
+        })
+        .collect()
+}
+
+/// Escapes text that is placed between tags, eg: the contents of a `<p>` or an `<a>`.
+fn escape_text(text: &str) -> String {
+    escape(text, "&<>\"")
+}
+
+/// Escapes text that is placed inside a double or single quoted attribute value.
+fn escape_attribute(value: &str) -> String {
+    escape(value, "&<>\"'")
+}
+
+/// Escapes the body of inline code and codeblocks. Quotes are left alone since they are common
+/// in code and harmless between tags.
+fn escape_code(code: &str) -> String {
+    escape(code, "&<>")
+}
+
+fn escape(text: &str, special_chars: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for it in text.chars() {
+        if !special_chars.contains(it) {
+            escaped.push(it);
+            continue;
+        }
+        escaped.push_str(match it {
+            '&' => "&amp;",
+            '<' => "&lt;",
+            '>' => "&gt;",
+            '"' => "&quot;",
+            _ => "&#39;",
+        });
+    }
+    escaped
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -112,6 +536,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_translate_strikethrough() {
+        assert_eq!(
+            translate_strikethrough(String::from("deleted")),
+            String::from("<del>deleted</del>")
+        );
+        assert_eq!(
+            translate_text(vec![MarkdownInline::Strikethrough(vec![
+                MarkdownInline::Plaintext("a ".into()),
+                MarkdownInline::Bold(vec![MarkdownInline::Plaintext("b".into())]),
+            ])]),
+            String::from("<del>a <b>b</b></del>")
+        );
+    }
+
     #[test]
     fn test_translate_inline_code() {
         assert_eq!(
@@ -125,32 +564,63 @@ mod tests {
         assert_eq!(
             translate_link(
                 String::from("click me!"),
-                String::from("https://github.com")
+                String::from("https://github.com"),
+                None
             ),
             String::from("<a href=\"https://github.com\">click me!</a>")
         );
+        assert_eq!(
+            translate_link(
+                String::from("click me!"),
+                String::from("https://github.com"),
+                Some(String::from("\"GitHub\" & co"))
+            ),
+            String::from(
+                "<a href=\"https://github.com\" title=\"&quot;GitHub&quot; &amp; co\">click me!</a>"
+            )
+        );
     }
 
     #[test]
     fn test_translate_image() {
         assert_eq!(
-            translate_image(String::from("alt text"), String::from("https://github.com")),
+            translate_image(
+                String::from("alt text"),
+                String::from("https://github.com"),
+                None
+            ),
             String::from("<img src=\"https://github.com\" alt=\"alt text\" />")
         );
+        assert_eq!(
+            translate_image(
+                String::from("alt text"),
+                String::from("https://github.com"),
+                Some(String::from("A cat"))
+            ),
+            String::from("<img src=\"https://github.com\" alt=\"alt text\" title=\"A cat\" />")
+        );
     }
 
     #[test]
     fn test_translate_text() {
         let x = translate_text(vec![
             MarkdownInline::Plaintext(
-                "Foobar is a Python library for dealing with word pluralization.",
+                "Foobar is a Python library for dealing with word pluralization.".into(),
             ),
-            MarkdownInline::Bold("bold"),
-            MarkdownInline::Italic("italic"),
-            MarkdownInline::InlineCode("code"),
-            MarkdownInline::Link(("tag", "https://link.com")),
-            MarkdownInline::Image(("tag", "https://link.com")),
-            MarkdownInline::Plaintext(". the end!"),
+            MarkdownInline::Bold(vec![MarkdownInline::Plaintext("bold".into())]),
+            MarkdownInline::Italic(vec![MarkdownInline::Plaintext("italic".into())]),
+            MarkdownInline::InlineCode("code".into()),
+            MarkdownInline::Link(Link {
+                text: vec![MarkdownInline::Plaintext("tag".into())],
+                url: "https://link.com".into(),
+                title: None,
+            }),
+            MarkdownInline::Image(Image {
+                alt: "tag".into(),
+                url: "https://link.com".into(),
+                title: None,
+            }),
+            MarkdownInline::Plaintext(". the end!".into()),
         ]);
         assert_eq!(x, String::from("Foobar is a Python library for dealing with word pluralization.<b>bold</b><i>italic</i><code>code</code><a href=\"https://link.com\">tag</a><img src=\"https://link.com\" alt=\"tag\" />. the end!"));
         let x = translate_text(vec![]);
@@ -162,7 +632,7 @@ mod tests {
         assert_eq!(
             translate_header(
                 &HeadingLevel::Heading1,
-                vec![MarkdownInline::Plaintext("Foobar")]
+                vec![MarkdownInline::Plaintext("Foobar".into())]
             ),
             String::from("<h1>Foobar</h1>")
         );
@@ -172,10 +642,22 @@ mod tests {
     fn test_translate_list_elements() {
         assert_eq!(
             translate_list_elements(vec![
-                vec![MarkdownInline::Plaintext("Foobar")],
-                vec![MarkdownInline::Plaintext("Foobar")],
-                vec![MarkdownInline::Plaintext("Foobar")],
-                vec![MarkdownInline::Plaintext("Foobar")],
+                vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "Foobar".into()
+                )])]
+                .into(),
+                vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "Foobar".into()
+                )])]
+                .into(),
+                vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "Foobar".into()
+                )])]
+                .into(),
+                vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "Foobar".into()
+                )])]
+                .into(),
             ]),
             String::from("<li>Foobar</li><li>Foobar</li><li>Foobar</li><li>Foobar</li>")
         );
@@ -185,10 +667,22 @@ mod tests {
     fn test_translate_unordered_list() {
         assert_eq!(
             translate_unordered_list(vec![
-                vec![MarkdownInline::Plaintext("Foobar")],
-                vec![MarkdownInline::Plaintext("Foobar")],
-                vec![MarkdownInline::Plaintext("Foobar")],
-                vec![MarkdownInline::Plaintext("Foobar")],
+                vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "Foobar".into()
+                )])]
+                .into(),
+                vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "Foobar".into()
+                )])]
+                .into(),
+                vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "Foobar".into()
+                )])]
+                .into(),
+                vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "Foobar".into()
+                )])]
+                .into(),
             ]),
             String::from("<ul><li>Foobar</li><li>Foobar</li><li>Foobar</li><li>Foobar</li></ul>")
         );
@@ -198,10 +692,22 @@ mod tests {
     fn test_translate_ordered_list() {
         assert_eq!(
             translate_ordered_list(vec![
-                vec![MarkdownInline::Plaintext("Foobar")],
-                vec![MarkdownInline::Plaintext("Foobar")],
-                vec![MarkdownInline::Plaintext("Foobar")],
-                vec![MarkdownInline::Plaintext("Foobar")],
+                vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "Foobar".into()
+                )])]
+                .into(),
+                vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "Foobar".into()
+                )])]
+                .into(),
+                vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "Foobar".into()
+                )])]
+                .into(),
+                vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "Foobar".into()
+                )])]
+                .into(),
             ]),
             String::from("<ol><li>Foobar</li><li>Foobar</li><li>Foobar</li><li>Foobar</li></ol>")
         );
@@ -211,7 +717,7 @@ mod tests {
     fn test_translate_codeblock() {
         assert_eq!(
             translate_codeblock(
-                String::from("python"),
+                Some(String::from("python")),
                 String::from(
                     r#"
 import foobar
@@ -232,18 +738,415 @@ foobar.singularize(\'phenomena\') # returns \'phenomenon\'
 </code></pre>"#
             )
         );
+        assert_eq!(
+            translate_codeblock(None, String::from("x < y\n")),
+            String::from("<pre><code>x &lt; y\n</code></pre>")
+        );
     }
 
     #[test]
-    fn test_translate_line() {
+    fn test_translate_paragraph() {
         assert_eq!(
-            translate_line(vec![
-                MarkdownInline::Plaintext("Foobar"),
-                MarkdownInline::Bold("Foobar"),
-                MarkdownInline::Italic("Foobar"),
-                MarkdownInline::InlineCode("Foobar"),
+            translate_paragraph(vec![
+                MarkdownInline::Plaintext("Foobar".into()),
+                MarkdownInline::Bold(vec![MarkdownInline::Plaintext("Foobar".into())]),
+                MarkdownInline::Italic(vec![MarkdownInline::Plaintext("Foobar".into())]),
+                MarkdownInline::InlineCode("Foobar".into()),
             ]),
             String::from("<p>Foobar<b>Foobar</b><i>Foobar</i><code>Foobar</code></p>")
         );
     }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape_text("<script>alert('&')</script> \"quoted\""),
+            String::from("&lt;script&gt;alert('&amp;')&lt;/script&gt; &quot;quoted&quot;")
+        );
+        assert_eq!(
+            escape_attribute("https://a.com/?a=1&b=\"2\"'><script>"),
+            String::from("https://a.com/?a=1&amp;b=&quot;2&quot;&#39;&gt;&lt;script&gt;")
+        );
+        assert_eq!(
+            escape_code("if a < b && b > \"c\" {}"),
+            String::from("if a &lt; b &amp;&amp; b &gt; \"c\" {}")
+        );
+        assert_eq!(escape_text("nothing to see here"), "nothing to see here");
+        assert_eq!(escape_text(""), "");
+    }
+
+    #[test]
+    fn test_translate_text_escapes_every_inline() {
+        assert_eq!(
+            translate_text(vec![MarkdownInline::Plaintext("<script>x</script>".into())]),
+            String::from("&lt;script&gt;x&lt;/script&gt;")
+        );
+        assert_eq!(
+            translate_text(vec![MarkdownInline::Bold(vec![MarkdownInline::Plaintext(
+                "a < b".into()
+            )])]),
+            String::from("<b>a &lt; b</b>")
+        );
+        assert_eq!(
+            translate_text(vec![MarkdownInline::Italic(vec![
+                MarkdownInline::Plaintext("a & b".into())
+            ])]),
+            String::from("<i>a &amp; b</i>")
+        );
+        assert_eq!(
+            translate_text(vec![MarkdownInline::Italic(vec![MarkdownInline::Bold(
+                vec![MarkdownInline::Plaintext("<em>".into())]
+            )])]),
+            String::from("<i><b>&lt;em&gt;</b></i>")
+        );
+        assert_eq!(
+            translate_text(vec![MarkdownInline::InlineCode("Vec<&str>".into())]),
+            String::from("<code>Vec&lt;&amp;str&gt;</code>")
+        );
+        assert_eq!(
+            translate_text(vec![MarkdownInline::Link(Link {
+                text: vec![MarkdownInline::Plaintext("<b>me</b>".into())],
+                url: "https://a.com/\" onclick=\"alert(1)".into(),
+                title: None
+            })]),
+            String::from(
+                "<a href=\"https://a.com/&quot; onclick=&quot;alert(1)\">&lt;b&gt;me&lt;/b&gt;</a>"
+            )
+        );
+        assert_eq!(
+            translate_text(vec![MarkdownInline::Image(Image {
+                alt: "\" onerror=\"alert(1)".into(),
+                url: "cat.png?a=1&b=2".into(),
+                title: None
+            })]),
+            String::from(
+                "<img src=\"cat.png?a=1&amp;b=2\" alt=\"&quot; onerror=&quot;alert(1)\" />"
+            )
+        );
+    }
+
+    #[test]
+    fn test_translate_escapes_every_block() {
+        assert_eq!(
+            translate(vec![
+                Markdown::Heading(
+                    HeadingLevel::Heading2,
+                    vec![MarkdownInline::Plaintext("1 < 2".into())]
+                ),
+                Markdown::UnorderedList(vec![vec![Markdown::Paragraph(vec![
+                    MarkdownInline::Plaintext("<li>".into())
+                ])]
+                .into()]),
+                Markdown::OrderedList(vec![vec![Markdown::Paragraph(vec![
+                    MarkdownInline::Plaintext("&".into())
+                ])]
+                .into()]),
+                Markdown::Codeblock(CodeBlock {
+                    lang: Some("\"><script>".into()),
+                    attributes: None,
+                    body: "<div class=\"x\">&nbsp;</div>\n".into()
+                }),
+                Markdown::Paragraph(vec![MarkdownInline::Plaintext("</p><script>".into())]),
+            ]),
+            String::from(concat!(
+                "<h2>1 &lt; 2</h2>",
+                "<ul><li>&lt;li&gt;</li></ul>",
+                "<ol><li>&amp;</li></ol>",
+                "<pre><code class=\"lang-&quot;&gt;&lt;script&gt;\">",
+                "&lt;div class=\"x\"&gt;&amp;nbsp;&lt;/div&gt;\n</code></pre>",
+                "<p>&lt;/p&gt;&lt;script&gt;</p>",
+            ))
+        );
+    }
+
+    #[test]
+    fn test_translate_default_url_policy() {
+        let md = vec![Markdown::Paragraph(vec![
+            MarkdownInline::Link(Link {
+                text: vec![MarkdownInline::Plaintext("x".into())],
+                url: "javascript:alert(1)".into(),
+                title: None,
+            }),
+            MarkdownInline::Plaintext(" ".into()),
+            MarkdownInline::Image(Image {
+                alt: "cat".into(),
+                url: "data:image/png;base64,AAAA".into(),
+                title: None,
+            }),
+            MarkdownInline::Plaintext(" ".into()),
+            MarkdownInline::Link(Link {
+                text: vec![MarkdownInline::Plaintext("ok".into())],
+                url: "https://github.com".into(),
+                title: None,
+            }),
+            MarkdownInline::Plaintext(" ".into()),
+            MarkdownInline::Autolink(Autolink {
+                text: "vbscript:msgbox(1)".into(),
+                url: "vbscript:msgbox(1)".into(),
+            }),
+        ])];
+        assert_eq!(
+            translate(md.clone()),
+            String::from("<p>x cat <a href=\"https://github.com\">ok</a> vbscript:msgbox(1)</p>")
+        );
+        assert_eq!(
+            translate_with_policy(md, &UrlPolicy::default()).rejected_urls,
+            vec![
+                RejectedUrl {
+                    kind: UrlKind::Link,
+                    url: String::from("javascript:alert(1)"),
+                    text: String::from("x"),
+                },
+                RejectedUrl {
+                    kind: UrlKind::Image,
+                    url: String::from("data:image/png;base64,AAAA"),
+                    text: String::from("cat"),
+                },
+                RejectedUrl {
+                    kind: UrlKind::Link,
+                    url: String::from("vbscript:msgbox(1)"),
+                    text: String::from("vbscript:msgbox(1)"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_translate_neutralize_url_policy() {
+        let policy = UrlPolicy {
+            on_reject: RejectAction::Neutralize,
+            ..UrlPolicy::default()
+        };
+        let translation = translate_with_policy(
+            vec![
+                Markdown::Heading(
+                    HeadingLevel::Heading1,
+                    vec![MarkdownInline::Link(Link {
+                        text: vec![MarkdownInline::Plaintext("x".into())],
+                        url: "vbscript:msgbox(1)".into(),
+                        title: None,
+                    })],
+                ),
+                Markdown::UnorderedList(vec![vec![Markdown::Paragraph(vec![
+                    MarkdownInline::Image(Image {
+                        alt: "cat".into(),
+                        url: "javascript:alert(1)".into(),
+                        title: None,
+                    }),
+                ])]
+                .into()]),
+                Markdown::OrderedList(vec![vec![Markdown::Paragraph(vec![MarkdownInline::Link(
+                    Link {
+                        text: vec![MarkdownInline::Plaintext("ok".into())],
+                        url: "/relative".into(),
+                        title: None,
+                    },
+                )])]
+                .into()]),
+            ],
+            &policy,
+        );
+        assert_eq!(
+            translation.html,
+            String::from(concat!(
+                "<h1><a href=\"#\">x</a></h1>",
+                "<ul><li><img src=\"#\" alt=\"cat\" /></li></ul>",
+                "<ol><li><a href=\"/relative\">ok</a></li></ol>",
+            ))
+        );
+        assert_eq!(
+            translation
+                .rejected_urls
+                .iter()
+                .map(|it| it.url.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["vbscript:msgbox(1)", "javascript:alert(1)"]
+        );
+    }
+
+    #[test]
+    fn test_translate_block_quote() {
+        assert_eq!(
+            translate_block_quote(vec![
+                Markdown::Paragraph(vec![MarkdownInline::Plaintext("quoted".into())]),
+                Markdown::BlockQuote(vec![Markdown::Paragraph(vec![MarkdownInline::Link(
+                    Link {
+                        text: vec![MarkdownInline::Plaintext("x".into())],
+                        url: "/x".into(),
+                        title: None
+                    }
+                )])]),
+            ]),
+            String::from(concat!(
+                "<blockquote><p>quoted</p>",
+                "<blockquote><p><a href=\"/x\">x</a></p></blockquote></blockquote>"
+            ))
+        );
+        assert_eq!(
+            translate(vec![Markdown::BlockQuote(vec![Markdown::Paragraph(vec![
+                MarkdownInline::Link(Link {
+                    text: vec![MarkdownInline::Plaintext("x".into())],
+                    url: "javascript:alert(1)".into(),
+                    title: None
+                })
+            ])])]),
+            String::from("<blockquote><p>x</p></blockquote>")
+        );
+    }
+
+    #[test]
+    fn test_translate_nested_list() {
+        assert_eq!(
+            translate_unordered_list(vec![
+                vec![
+                    Markdown::Paragraph(vec![
+                        MarkdownInline::Plaintext("one".into()),
+                        MarkdownInline::SoftBreak,
+                        MarkdownInline::Plaintext("more".into()),
+                    ]),
+                    Markdown::OrderedList(vec![vec![Markdown::Paragraph(vec![
+                        MarkdownInline::Plaintext("one.one".into())
+                    ])]
+                    .into()]),
+                ]
+                .into(),
+                vec![
+                    Markdown::Paragraph(vec![MarkdownInline::Plaintext("two".into())]),
+                    Markdown::Codeblock(CodeBlock {
+                        lang: Some("rust".into()),
+                        attributes: None,
+                        body: "let a = 1;\n".into()
+                    }),
+                ]
+                .into(),
+                vec![
+                    Markdown::Paragraph(vec![MarkdownInline::Plaintext("three".into())]),
+                    Markdown::Paragraph(vec![MarkdownInline::Plaintext("more".into())]),
+                ]
+                .into(),
+            ]),
+            String::from(concat!(
+                "<ul>",
+                "<li>one\nmore<ol><li>one.one</li></ol></li>",
+                "<li>two<pre><code class=\"lang-rust\">let a = 1;\n</code></pre></li>",
+                "<li><p>three</p><p>more</p></li>",
+                "</ul>"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_translate_table() {
+        assert_eq!(
+            translate(vec![Markdown::Table(Table {
+                header: vec![
+                    vec![MarkdownInline::Plaintext("a".into())],
+                    vec![MarkdownInline::Bold(vec![MarkdownInline::Plaintext(
+                        "b".into()
+                    )])],
+                ],
+                alignments: vec![Alignment::Left, Alignment::None],
+                rows: vec![vec![
+                    vec![MarkdownInline::Plaintext("1 < 2".into())],
+                    vec![],
+                ]],
+            })]),
+            String::from(concat!(
+                "<table><thead><tr><th align=\"left\">a</th><th><b>b</b></th></tr></thead>",
+                "<tbody><tr><td align=\"left\">1 &lt; 2</td><td></td></tr></tbody></table>"
+            ))
+        );
+        assert_eq!(
+            translate(vec![Markdown::Table(Table {
+                header: vec![vec![MarkdownInline::Plaintext("a".into())]],
+                alignments: vec![Alignment::Right],
+                rows: vec![],
+            })]),
+            String::from("<table><thead><tr><th align=\"right\">a</th></tr></thead></table>")
+        );
+    }
+
+    #[test]
+    fn test_translate_task_list() {
+        assert_eq!(
+            translate_unordered_list(vec![
+                ListItem {
+                    checked: Some(false),
+                    blocks: vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                        "todo".into()
+                    )])],
+                },
+                ListItem {
+                    checked: Some(true),
+                    blocks: vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                        "done".into()
+                    )])],
+                },
+                vec![Markdown::Paragraph(vec![MarkdownInline::Plaintext(
+                    "plain".into()
+                )])]
+                .into(),
+            ]),
+            String::from(concat!(
+                "<ul class=\"contains-task-list\">",
+                "<li><input type=\"checkbox\" disabled /> todo</li>",
+                "<li><input type=\"checkbox\" disabled checked /> done</li>",
+                "<li>plain</li>",
+                "</ul>"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_translate_line_breaks() {
+        assert_eq!(
+            translate_paragraph(vec![
+                MarkdownInline::Plaintext("one".into()),
+                MarkdownInline::HardBreak,
+                MarkdownInline::Plaintext("two".into()),
+                MarkdownInline::SoftBreak,
+                MarkdownInline::Plaintext("three".into()),
+            ]),
+            String::from("<p>one<br />two\nthree</p>")
+        );
+    }
+
+    #[test]
+    fn test_translate_nested_text() {
+        let text = vec![MarkdownInline::Bold(vec![
+            MarkdownInline::Plaintext("see ".into()),
+            MarkdownInline::Link(Link {
+                text: vec![MarkdownInline::Italic(vec![MarkdownInline::Plaintext(
+                    "a < b".into(),
+                )])],
+                url: "javascript:alert(1)".into(),
+                title: None,
+            }),
+        ])];
+        assert_eq!(
+            translate_with_policy(
+                vec![Markdown::Paragraph(text.clone())],
+                &UrlPolicy {
+                    on_reject: RejectAction::Neutralize,
+                    ..UrlPolicy::default()
+                }
+            )
+            .html,
+            String::from("<p><b>see <a href=\"#\"><i>a &lt; b</i></a></b></p>")
+        );
+        let translation =
+            translate_with_policy(vec![Markdown::Paragraph(text)], &UrlPolicy::default());
+        assert_eq!(
+            translation.html,
+            String::from("<p><b>see <i>a &lt; b</i></b></p>")
+        );
+        assert_eq!(
+            translation.rejected_urls,
+            vec![RejectedUrl {
+                kind: UrlKind::Link,
+                url: "javascript:alert(1)".into(),
+                text: "a < b".into(),
+            }]
+        );
+    }
 }
@@ -1,12 +1,116 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
 pub type MarkdownText<'a> = Vec<MarkdownInline<'a>>;
 
+/// An item of an ordered or unordered list.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ListItem<'a> {
+    /// `Some` for task list items, eg: `Some(false)` for `- [ ] todo` and `Some(true)` for
+    /// `- [x] done`.
+    pub checked: Option<bool>,
+    /// The blocks in the item, eg: a line of text followed by a nested list.
+    pub blocks: Vec<Markdown<'a>>,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Markdown<'a> {
     Heading(HeadingLevel, MarkdownText<'a>),
-    OrderedList(Vec<MarkdownText<'a>>),
-    UnorderedList(Vec<MarkdownText<'a>>),
-    Line(MarkdownText<'a>),
-    Codeblock(&'a str, &'a str),
+    OrderedList(Vec<ListItem<'a>>),
+    UnorderedList(Vec<ListItem<'a>>),
+    /// Consecutive lines of text, joined with [MarkdownInline::SoftBreak] or
+    /// [MarkdownInline::HardBreak].
+    Paragraph(MarkdownText<'a>),
+    Codeblock(CodeBlock<'a>),
+    BlockQuote(Vec<Markdown<'a>>),
+    Table(Table<'a>),
+    /// A horizontal rule, eg: `---` or `* * *`.
+    ThematicBreak,
+    /// Removed by [crate::try_parse_markdown] once the links that refer to it are resolved, see
+    /// [crate::resolve_link_references].
+    LinkDefinition(LinkDefinition<'a>),
+    /// Removed by [crate::try_parse_markdown], which moves the footnotes into a
+    /// [Markdown::Footnotes] at the end of the document, see [crate::resolve_footnotes].
+    FootnoteDefinition(FootnoteDefinition<'a>),
+    Footnotes(Vec<Footnote<'a>>),
+    /// Only found at the very start of a document, and never rendered.
+    FrontMatter(FrontMatter<'a>),
+}
+
+/// A codeblock, either fenced by three or more backticks or tildes (and closed by at least as
+/// many of the same char), or indented by four spaces. Indented codeblocks have no info string.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CodeBlock<'a> {
+    /// The first word of the info string after the opening fence, eg: `rust` in ```` ```rust ````.
+    pub lang: Option<Cow<'a, str>>,
+    /// The rest of the info string, eg: `title="main.rs"` in ```` ```rust title="main.rs" ````.
+    pub attributes: Option<Cow<'a, str>>,
+    /// Line endings in the body are normalized to `\n`.
+    pub body: Cow<'a, str>,
+}
+
+/// The target of reference links, eg: `[ref]: /url "title"`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkDefinition<'a> {
+    /// Matched case-insensitively, see [crate::normalize_link_label].
+    pub label: Cow<'a, str>,
+    pub url: Cow<'a, str>,
+    pub title: Option<Cow<'a, str>>,
+}
+
+/// The text of a footnote, eg: `[^1]: text`. The lines after the first that are indented by four
+/// spaces belong to it too, so it can hold several paragraphs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FootnoteDefinition<'a> {
+    /// Matched case-insensitively, like the label of a [LinkDefinition].
+    pub label: Cow<'a, str>,
+    pub blocks: Vec<Markdown<'a>>,
+}
+
+/// A footnote in [Markdown::Footnotes]. The footnotes that are referenced come first, in the order
+/// they are first referenced, so the first one is footnote 1. The ones that are never referenced
+/// come after them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Footnote<'a> {
+    pub label: Cow<'a, str>,
+    pub blocks: Vec<Markdown<'a>>,
+    /// How many times the footnote is referenced.
+    pub references: usize,
+}
+
+/// Metadata at the start of a document, either YAML between `---` lines or TOML between `+++`
+/// lines, eg: `title: Hello` or `title = "Hello"`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrontMatter<'a> {
+    pub format: FrontMatterFormat,
+    /// The text between the opening and closing lines, with its line endings normalized to `\n`.
+    pub raw: Cow<'a, str>,
+    /// The top-level keys and their values, see [crate::parse_front_matter_fields].
+    pub fields: HashMap<String, String>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FrontMatterFormat {
+    Yaml,
+    Toml,
+}
+
+/// A GFM pipe table. Every row has as many cells as there are `alignments`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Table<'a> {
+    pub header: Vec<MarkdownText<'a>>,
+    pub alignments: Vec<Alignment>,
+    pub rows: Vec<Vec<MarkdownText<'a>>>,
+}
+
+/// The alignment of a table column, from the `:---`, `:---:` or `---:` in its delimiter row.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Alignment {
+    None,
+    Left,
+    Center,
+    Right,
 }
 
 #[repr(u8)]
@@ -20,27 +124,227 @@ pub enum HeadingLevel {
     Heading6,
 }
 
-impl From<usize> for HeadingLevel {
-    fn from(size: usize) -> Self {
+impl TryFrom<usize> for HeadingLevel {
+    /// The size that is not a heading level, ie: not from 1 to 6.
+    type Error = usize;
+
+    fn try_from(size: usize) -> Result<Self, Self::Error> {
         match size {
-            1 => HeadingLevel::Heading1,
-            2 => HeadingLevel::Heading2,
-            3 => HeadingLevel::Heading3,
-            4 => HeadingLevel::Heading4,
-            5 => HeadingLevel::Heading5,
-            6 => HeadingLevel::Heading6,
-            _ => HeadingLevel::Heading6,
+            1 => Ok(HeadingLevel::Heading1),
+            2 => Ok(HeadingLevel::Heading2),
+            3 => Ok(HeadingLevel::Heading3),
+            4 => Ok(HeadingLevel::Heading4),
+            5 => Ok(HeadingLevel::Heading5),
+            6 => Ok(HeadingLevel::Heading6),
+            _ => Err(size),
         }
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum MarkdownInline<'a> {
-    Link((&'a str, &'a str)),
-    Image((&'a str, &'a str)),
-    InlineCode(&'a str),
-    Bold(&'a str),
-    BoldItalic(&'a str),
-    Italic(&'a str),
-    Plaintext(&'a str),
+    Link(Link<'a>),
+    /// A link to a [LinkDefinition], eg: `[text][ref]`, `[ref][]` or `[ref]`. Holds the link text,
+    /// the label, and the source after the text (eg: `][ref]`), which is kept as plaintext if
+    /// there is no definition. [crate::try_parse_markdown] resolves these.
+    LinkReference((MarkdownText<'a>, Cow<'a, str>, Cow<'a, str>)),
+    Image(Image<'a>),
+    Autolink(Autolink<'a>),
+    FootnoteReference(FootnoteReference<'a>),
+    InlineCode(Cow<'a, str>),
+    Bold(MarkdownText<'a>),
+    Italic(MarkdownText<'a>),
+    /// Text between `~~`, eg: `~~deleted~~`.
+    Strikethrough(MarkdownText<'a>),
+    Plaintext(Cow<'a, str>),
+    /// The end of a line inside a paragraph.
+    SoftBreak,
+    /// The end of a line that ends with two or more spaces or a `\\`, which forces a line break.
+    HardBreak,
+}
+
+/// A link, eg: `[text](url "title")`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Link<'a> {
+    pub text: MarkdownText<'a>,
+    pub url: Cow<'a, str>,
+    pub title: Option<Cow<'a, str>>,
+}
+
+/// An image, eg: `![alt text](url "title")`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image<'a> {
+    pub alt: Cow<'a, str>,
+    pub url: Cow<'a, str>,
+    pub title: Option<Cow<'a, str>>,
+}
+
+/// A reference to a footnote, eg: `[^1]`. The parser leaves `number` and `index` empty, and
+/// [crate::try_parse_markdown] fills them in, see [crate::resolve_footnotes].
+#[derive(Clone, Debug, PartialEq)]
+pub struct FootnoteReference<'a> {
+    pub label: Cow<'a, str>,
+    /// The number of the footnote, or `None` if there is no footnote with the label.
+    pub number: Option<usize>,
+    /// Counts the references to the same footnote from 1, so each one can have its own backlink.
+    pub index: usize,
+}
+
+/// A URL or email address that is a link by itself, eg: `<https://example.com>`,
+/// `<me@example.com>` or `www.example.com`. `url` is the link target, eg: with `mailto:` in front
+/// of an email address.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Autolink<'a> {
+    pub text: Cow<'a, str>,
+    pub url: Cow<'a, str>,
+}
+
+impl Markdown<'_> {
+    /// Copies any text that is borrowed from the input, so the result can outlive it. This is
+    /// used for blocks that are parsed from text that had to be rewritten, eg: the contents of a
+    /// blockquote with the `>` markers removed.
+    pub fn into_owned(self) -> Markdown<'static> {
+        match self {
+            Markdown::Heading(level, text) => Markdown::Heading(level, text_into_owned(text)),
+            Markdown::OrderedList(items) => {
+                Markdown::OrderedList(items.into_iter().map(ListItem::into_owned).collect())
+            }
+            Markdown::UnorderedList(items) => {
+                Markdown::UnorderedList(items.into_iter().map(ListItem::into_owned).collect())
+            }
+            Markdown::Paragraph(text) => Markdown::Paragraph(text_into_owned(text)),
+            Markdown::Codeblock(CodeBlock {
+                lang,
+                attributes,
+                body,
+            }) => Markdown::Codeblock(CodeBlock {
+                lang: lang.map(owned),
+                attributes: attributes.map(owned),
+                body: owned(body),
+            }),
+            Markdown::BlockQuote(blocks) => Markdown::BlockQuote(blocks_into_owned(blocks)),
+            Markdown::Table(Table {
+                header,
+                alignments,
+                rows,
+            }) => Markdown::Table(Table {
+                header: header.into_iter().map(text_into_owned).collect(),
+                alignments,
+                rows: rows
+                    .into_iter()
+                    .map(|row| row.into_iter().map(text_into_owned).collect())
+                    .collect(),
+            }),
+            Markdown::ThematicBreak => Markdown::ThematicBreak,
+            Markdown::LinkDefinition(LinkDefinition { label, url, title }) => {
+                Markdown::LinkDefinition(LinkDefinition {
+                    label: owned(label),
+                    url: owned(url),
+                    title: title.map(owned),
+                })
+            }
+            Markdown::FootnoteDefinition(FootnoteDefinition { label, blocks }) => {
+                Markdown::FootnoteDefinition(FootnoteDefinition {
+                    label: owned(label),
+                    blocks: blocks_into_owned(blocks),
+                })
+            }
+            Markdown::Footnotes(footnotes) => Markdown::Footnotes(
+                footnotes
+                    .into_iter()
+                    .map(|footnote| Footnote {
+                        label: owned(footnote.label),
+                        blocks: blocks_into_owned(footnote.blocks),
+                        references: footnote.references,
+                    })
+                    .collect(),
+            ),
+            Markdown::FrontMatter(FrontMatter {
+                format,
+                raw,
+                fields,
+            }) => Markdown::FrontMatter(FrontMatter {
+                format,
+                raw: owned(raw),
+                fields,
+            }),
+        }
+    }
+}
+
+impl ListItem<'_> {
+    /// See [Markdown::into_owned].
+    pub fn into_owned(self) -> ListItem<'static> {
+        ListItem {
+            checked: self.checked,
+            blocks: blocks_into_owned(self.blocks),
+        }
+    }
+}
+
+impl<'a> From<Vec<Markdown<'a>>> for ListItem<'a> {
+    /// A list item that is not a task list item.
+    fn from(blocks: Vec<Markdown<'a>>) -> Self {
+        ListItem {
+            checked: None,
+            blocks,
+        }
+    }
+}
+
+impl MarkdownInline<'_> {
+    /// See [Markdown::into_owned].
+    pub fn into_owned(self) -> MarkdownInline<'static> {
+        match self {
+            MarkdownInline::Link(Link { text, url, title }) => MarkdownInline::Link(Link {
+                text: text_into_owned(text),
+                url: owned(url),
+                title: title.map(owned),
+            }),
+            MarkdownInline::LinkReference((text, label, closing)) => {
+                MarkdownInline::LinkReference((text_into_owned(text), owned(label), owned(closing)))
+            }
+            MarkdownInline::Image(Image { alt, url, title }) => MarkdownInline::Image(Image {
+                alt: owned(alt),
+                url: owned(url),
+                title: title.map(owned),
+            }),
+            MarkdownInline::Autolink(Autolink { text, url }) => {
+                MarkdownInline::Autolink(Autolink {
+                    text: owned(text),
+                    url: owned(url),
+                })
+            }
+            MarkdownInline::FootnoteReference(FootnoteReference {
+                label,
+                number,
+                index,
+            }) => MarkdownInline::FootnoteReference(FootnoteReference {
+                label: owned(label),
+                number,
+                index,
+            }),
+            MarkdownInline::InlineCode(code) => MarkdownInline::InlineCode(owned(code)),
+            MarkdownInline::Bold(text) => MarkdownInline::Bold(text_into_owned(text)),
+            MarkdownInline::Italic(text) => MarkdownInline::Italic(text_into_owned(text)),
+            MarkdownInline::Strikethrough(text) => {
+                MarkdownInline::Strikethrough(text_into_owned(text))
+            }
+            MarkdownInline::Plaintext(text) => MarkdownInline::Plaintext(owned(text)),
+            MarkdownInline::SoftBreak => MarkdownInline::SoftBreak,
+            MarkdownInline::HardBreak => MarkdownInline::HardBreak,
+        }
+    }
+}
+
+fn blocks_into_owned(blocks: Vec<Markdown<'_>>) -> Vec<Markdown<'static>> {
+    blocks.into_iter().map(Markdown::into_owned).collect()
+}
+
+fn text_into_owned(text: MarkdownText<'_>) -> MarkdownText<'static> {
+    text.into_iter().map(MarkdownInline::into_owned).collect()
+}
+
+fn owned(text: Cow<'_, str>) -> Cow<'static, str> {
+    Cow::Owned(text.into_owned())
 }
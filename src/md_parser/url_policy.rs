@@ -0,0 +1,169 @@
+/// Decides which link and image URLs are allowed to end up in `href` and `src` attributes.
+/// The default policy allows `http`, `https` and `mailto` links, relative and protocol-relative
+/// URLs, and rejects everything else (eg: `javascript:` and `data:`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct UrlPolicy {
+    /// Schemes (without the trailing `:`) that are allowed. Compared case-insensitively.
+    pub allowed_schemes: Vec<String>,
+    /// Allow URLs without a scheme, eg: `image.jpg`, `/docs`, `#section` or `?page=2`.
+    pub allow_relative: bool,
+    /// Allow URLs that start with `//`, which inherit the scheme of the page.
+    pub allow_protocol_relative: bool,
+    /// Allow `data:image/...` URLs for images (never for links). SVG is always rejected since it
+    /// can carry scripts.
+    pub allow_data_images: bool,
+    /// What to do with a rejected URL.
+    pub on_reject: RejectAction,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RejectAction {
+    /// Render the link text (or image alt text) as plain text, without the element.
+    Drop,
+    /// Keep the element but replace the URL with [NEUTRALIZED_URL].
+    Neutralize,
+}
+
+/// The URL that replaces rejected URLs when [RejectAction::Neutralize] is used.
+pub const NEUTRALIZED_URL: &str = "#";
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UrlKind {
+    Link,
+    Image,
+}
+
+/// A URL that was rejected by a [UrlPolicy] while translating.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RejectedUrl {
+    pub kind: UrlKind,
+    pub url: String,
+    /// The link text or image alt text.
+    pub text: String,
+}
+
+impl Default for UrlPolicy {
+    fn default() -> Self {
+        UrlPolicy {
+            allowed_schemes: vec!["http".into(), "https".into(), "mailto".into()],
+            allow_relative: true,
+            allow_protocol_relative: true,
+            allow_data_images: false,
+            on_reject: RejectAction::Drop,
+        }
+    }
+}
+
+impl UrlPolicy {
+    pub fn is_allowed(&self, url: &str, kind: UrlKind) -> bool {
+        // Browsers ignore leading whitespace & control chars and drop tabs and newlines anywhere
+        // in a URL, so `" java\tscript:"` is still a `javascript:` URL.
+        let url = url
+            .trim_start_matches(|it: char| it <= ' ')
+            .replace(['\t', '\n', '\r'], "");
+
+        if url.starts_with("//") || url.starts_with("\\\\") || url.starts_with("/\\") {
+            return self.allow_protocol_relative;
+        }
+
+        match scheme(&url) {
+            None => self.allow_relative,
+            Some(scheme) if scheme.eq_ignore_ascii_case("data") => {
+                kind == UrlKind::Image && self.allow_data_images && is_safe_data_image(&url)
+            }
+            Some(scheme) => self
+                .allowed_schemes
+                .iter()
+                .any(|it| it.eq_ignore_ascii_case(scheme)),
+        }
+    }
+}
+
+/// Returns the scheme of `url` if it has one. A `:` that comes after a `/`, `?` or `#` belongs to
+/// the path, query or fragment of a relative URL and doesn't start a scheme.
+fn scheme(url: &str) -> Option<&str> {
+    let end = url.find([':', '/', '?', '#'])?;
+    if !url[end..].starts_with(':') {
+        return None;
+    }
+    let scheme = &url[..end];
+    let mut chars = scheme.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() => {}
+        _ => return None,
+    }
+    if chars.all(|it| it.is_ascii_alphanumeric() || matches!(it, '+' | '-' | '.')) {
+        Some(scheme)
+    } else {
+        None
+    }
+}
+
+fn is_safe_data_image(url: &str) -> bool {
+    let mime = url["data:".len()..]
+        .split([';', ','])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    matches!(
+        mime.as_str(),
+        "image/png" | "image/gif" | "image/jpeg" | "image/jpg" | "image/webp" | "image/avif"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scheme() {
+        assert_eq!(scheme("https://github.com"), Some("https"));
+        assert_eq!(scheme("mailto:me@example.com"), Some("mailto"));
+        assert_eq!(scheme("JavaScript:alert(1)"), Some("JavaScript"));
+        assert_eq!(scheme("image.jpg"), None);
+        assert_eq!(scheme("/docs/a:b"), None);
+        assert_eq!(scheme("?a=b:c"), None);
+        assert_eq!(scheme("#a:b"), None);
+        assert_eq!(scheme("1http:foo"), None);
+        assert_eq!(scheme(":foo"), None);
+    }
+
+    #[test]
+    fn test_default_policy() {
+        let policy = UrlPolicy::default();
+        assert!(policy.is_allowed("https://github.com", UrlKind::Link));
+        assert!(policy.is_allowed("HTTP://github.com", UrlKind::Link));
+        assert!(policy.is_allowed("mailto:me@example.com", UrlKind::Link));
+        assert!(policy.is_allowed("image.jpg", UrlKind::Image));
+        assert!(policy.is_allowed("/docs#install", UrlKind::Link));
+        assert!(policy.is_allowed("//cdn.example.com/cat.png", UrlKind::Image));
+        assert!(!policy.is_allowed("javascript:alert(1)", UrlKind::Link));
+        assert!(!policy.is_allowed("  JaVaScRiPt:alert(1)", UrlKind::Link));
+        assert!(!policy.is_allowed("java\tscript:alert(1)", UrlKind::Link));
+        assert!(!policy.is_allowed("java\nscript:alert(1)", UrlKind::Link));
+        assert!(!policy.is_allowed("vbscript:msgbox(1)", UrlKind::Link));
+        assert!(!policy.is_allowed("data:image/png;base64,AAAA", UrlKind::Image));
+        assert!(!policy.is_allowed("data:text/html,<script>", UrlKind::Link));
+    }
+
+    #[test]
+    fn test_configured_policy() {
+        let policy = UrlPolicy {
+            allowed_schemes: vec!["https".into()],
+            allow_relative: false,
+            allow_protocol_relative: false,
+            allow_data_images: true,
+            on_reject: RejectAction::Neutralize,
+        };
+        assert!(policy.is_allowed("https://github.com", UrlKind::Link));
+        assert!(!policy.is_allowed("http://github.com", UrlKind::Link));
+        assert!(!policy.is_allowed("image.jpg", UrlKind::Image));
+        assert!(!policy.is_allowed("//cdn.example.com/cat.png", UrlKind::Image));
+        assert!(policy.is_allowed("data:image/png;base64,AAAA", UrlKind::Image));
+        assert!(policy.is_allowed("DATA:image/GIF;base64,AAAA", UrlKind::Image));
+        assert!(!policy.is_allowed("data:image/png;base64,AAAA", UrlKind::Link));
+        assert!(!policy.is_allowed("data:image/svg+xml,<svg/>", UrlKind::Image));
+        assert!(!policy.is_allowed("data:text/html,<script>", UrlKind::Image));
+    }
+}